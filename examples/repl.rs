@@ -1,9 +1,15 @@
 use std::{env, error::Error, fs};
 
 use rustyline::{error::ReadlineError, Editor};
-use wmd::{interpreter::Interpreter, lexer::Lexer, parser::Parser, reporting::StdoutReporter};
+use wmd::{
+    analytics::{self, Stats},
+    interpreter::Interpreter,
+    lexer::Lexer,
+    parser::Parser,
+    reporting::StdoutReporter,
+};
 
-const HISTORY: &'static str = ".wmd-history.txt";
+const HISTORY: &str = ".wmd-history.txt";
 
 fn repl() -> Result<(), Box<dyn Error>> {
     let mut rl = Editor::<()>::new();
@@ -21,18 +27,17 @@ fn repl() -> Result<(), Box<dyn Error>> {
                 // println!("{tokens:#?}");
 
                 let mut parser = Parser::new(tokens, &reporter);
-                let expr = parser.parse();
-                println!("{expr:#?}");
-                // match expr {
-                //     Ok(expr) => match interpreter.evaluate(&expr) {
-                //         Ok(res) => {
-                //             println!("{res}");
-                //             rl.add_history_entry(line.as_str());
-                //         }
-                //         Err(e) => eprintln!("{e}"),
-                //     },
-                //     Err(e) => eprintln!("{e:?}"),
-                // }
+                let stmts = parser.parse();
+                match stmts {
+                    Ok(stmts) => match interpreter.interpret(&stmts) {
+                        Ok(res) => {
+                            println!("{res}");
+                            rl.add_history_entry(line.as_str());
+                        }
+                        Err(e) => eprintln!("{e}"),
+                    },
+                    Err(e) => eprintln!("{e:?}"),
+                }
             }
             Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
             Err(err) => {
@@ -56,17 +61,65 @@ fn run_file(path: &str) -> Result<(), Box<dyn Error>> {
     let tokens = lexer.scan_tokens();
 
     let mut parser = Parser::new(tokens, &reporter);
-    let expr = parser.parse();
-
-    // match expr {
-    //     Ok(expr) => match interpreter.evaluate(&expr) {
-    //         Ok(res) => {
-    //             println!("{res}");
-    //         }
-    //         Err(e) => eprintln!("{e}"),
-    //     },
-    //     Err(e) => eprintln!("{e:?}"),
-    // }
+    let stmts = parser.parse();
+
+    match stmts {
+        Ok(stmts) => match interpreter.interpret(&stmts) {
+            Ok(res) => {
+                println!("{res}");
+            }
+            Err(e) => eprintln!("{e}"),
+        },
+        Err(e) => eprintln!("{e:?}"),
+    }
+
+    Ok(())
+}
+
+/// Prints the timeline of the workout a file describes, or its stats or
+/// the stats of each week of the programs it declares
+fn report(command: &str, path: &str) -> Result<(), Box<dyn Error>> {
+    let src = fs::read_to_string(path)?;
+
+    let reporter = StdoutReporter;
+    let mut interpreter = Interpreter::new();
+
+    let lexer = Lexer::new(&src, &reporter);
+    let tokens = lexer.scan_tokens();
+
+    let mut parser = Parser::new(tokens, &reporter);
+    let stmts = match parser.parse() {
+        Ok(stmts) => stmts,
+        Err(e) => {
+            eprintln!("{e:?}");
+            return Ok(());
+        }
+    };
+    if let Err(e) = interpreter.interpret(&stmts) {
+        eprintln!("{e}");
+        return Ok(());
+    }
+
+    if command == "timeline" {
+        for segment in interpreter.workout().timeline() {
+            println!("{segment}");
+        }
+        return Ok(());
+    }
+
+    let programs = interpreter.take_programs();
+    if programs.is_empty() {
+        print!(
+            "{}",
+            Stats::of(interpreter.workout(), interpreter.profile())
+        );
+    }
+    for program in programs {
+        for (week, stats) in analytics::weekly(&program, interpreter.profile()) {
+            println!("{} week {week}", program.name);
+            println!("{stats}");
+        }
+    }
 
     Ok(())
 }
@@ -74,7 +127,9 @@ fn run_file(path: &str) -> Result<(), Box<dyn Error>> {
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<_> = env::args().collect();
 
-    if args.len() == 2 {
+    if args.len() == 3 && matches!(args[1].as_str(), "stats" | "timeline") {
+        report(&args[1], &args[2])
+    } else if args.len() == 2 {
         run_file(&args[1])
     } else {
         repl()
@@ -0,0 +1,325 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::{
+    ast::{Quantity, Reps, TimeUnit, Unit, WeightUnit},
+    profile::LifterProfile,
+    workout::{Program, SetPrescription, Target, Workout},
+};
+
+/// Labels of the %1RM zones counted in [`Stats::intensity`]
+pub const INTENSITY_ZONES: [&str; 5] = ["<60%", "60-70%", "70-80%", "80-90%", "90%+"];
+
+/// Work done for an exercise or category. Tonnage is in the weight unit
+/// most sets are loaded in and displayed to the nearest whole unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume {
+    pub sets: usize,
+    pub reps: u32,
+    pub tonnage: Quantity,
+}
+
+impl Volume {
+    fn new(unit: WeightUnit) -> Self {
+        Self {
+            sets: 0,
+            reps: 0,
+            tonnage: Quantity::new(0.0, Unit::Weight(unit)),
+        }
+    }
+
+    /// Tonnage is converted to the unit of the running total
+    fn add(&mut self, reps: u32, tonnage: Option<Quantity>) {
+        self.sets += 1;
+        self.reps += reps;
+        if let Some(tonnage) = tonnage.and_then(|t| t.convert_to(self.tonnage.unit())) {
+            self.tonnage = Quantity::new(self.tonnage.value() + tonnage.value(), tonnage.unit());
+        }
+    }
+}
+
+impl Display for Volume {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tonnage = Quantity::new(self.tonnage.value().round(), self.tonnage.unit());
+        write!(f, "{} sets, {} reps, {tonnage}", self.sets, self.reps)
+    }
+}
+
+/// Summary of one or more evaluated workouts.
+///
+/// Rep ranges count their low end. Loads given as an unresolved percentage
+/// add no tonnage, and sets only count towards an intensity zone if their
+/// %1RM is known from a percentage load or from a weight and the max in
+/// the profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    /// Unit tonnage is reported in
+    pub unit: WeightUnit,
+    pub volume: Volume,
+    /// Timed work plus the reps of sets with a tempo
+    pub time_under_tension: Duration,
+    pub rest: Duration,
+    /// Keyed by exercise name, in order of first appearance
+    pub exercises: Vec<(String, Volume)>,
+    /// Keyed by exercise category such as a muscle group
+    pub categories: Vec<(String, Volume)>,
+    /// Sets per zone of [`INTENSITY_ZONES`]
+    pub intensity: [usize; INTENSITY_ZONES.len()],
+}
+
+impl Stats {
+    pub fn of(workout: &Workout, profile: &LifterProfile) -> Self {
+        Self::of_all([workout], profile)
+    }
+
+    /// Stats of several workouts combined, such as the days of a week
+    pub fn of_all<'a>(
+        workouts: impl IntoIterator<Item = &'a Workout>,
+        profile: &LifterProfile,
+    ) -> Self {
+        let workouts: Vec<_> = workouts.into_iter().collect();
+        let unit = weight_unit(&workouts);
+        let mut stats = Stats {
+            unit,
+            volume: Volume::new(unit),
+            time_under_tension: Duration::ZERO,
+            rest: Duration::ZERO,
+            exercises: Vec::new(),
+            categories: Vec::new(),
+            intensity: [0; INTENSITY_ZONES.len()],
+        };
+
+        for workout in workouts {
+            for set in &workout.sets {
+                stats.add_set(set, profile);
+            }
+            stats.rest += workout.rests.iter().map(|r| r.duration).sum::<Duration>();
+        }
+
+        stats
+    }
+
+    fn add_set(&mut self, set: &SetPrescription, profile: &LifterProfile) {
+        let reps = match set.target {
+            Target::Reps(Reps::Fixed(reps) | Reps::Range(reps, _)) => reps,
+            Target::Amount(amount) => {
+                self.time_under_tension += amount.to_duration().unwrap_or_default();
+                0
+            }
+        };
+        if let Some(tempo) = set.tempo {
+            self.time_under_tension += tempo
+                .time_under_tension(reps as f64)
+                .to_duration()
+                .unwrap_or_default();
+        }
+
+        let tonnage = set
+            .load
+            .filter(|load| matches!(load.unit(), Unit::Weight(_)))
+            .map(|load| load.scale(reps as f64));
+        self.volume.add(reps, tonnage);
+
+        if let Some(exercise) = &set.exercise {
+            volume_of(&mut self.exercises, &exercise.name, self.unit).add(reps, tonnage);
+            if let Some(category) = &exercise.category {
+                volume_of(&mut self.categories, category, self.unit).add(reps, tonnage);
+            }
+        }
+
+        let percent = set.load.and_then(|load| match load.unit() {
+            Unit::Percent => Some(load.value()),
+            _ => {
+                let max = profile.max(set.exercise.as_deref()?)?;
+                Some(load.convert_to(max.unit())?.value() / max.value() * 100.0)
+            }
+        });
+        if let Some(percent) = percent {
+            let zone = ((percent - 50.0) / 10.0).clamp(0.0, 4.0) as usize;
+            self.intensity[zone] += 1;
+        }
+    }
+
+    /// Time under tension divided by rest, `None` if there is no rest
+    pub fn work_rest_ratio(&self) -> Option<f64> {
+        if self.rest.is_zero() {
+            None
+        } else {
+            Some(self.time_under_tension.as_secs_f64() / self.rest.as_secs_f64())
+        }
+    }
+}
+
+/// Stats of each week of a program with all its days combined
+pub fn weekly(program: &Program, profile: &LifterProfile) -> Vec<(u32, Stats)> {
+    program
+        .weeks
+        .iter()
+        .map(|week| (week.number, Stats::of_all(&week.days, profile)))
+        .collect()
+}
+
+/// Unit most loaded sets are in, kilograms if there are none or as many
+/// in pounds
+fn weight_unit(workouts: &[&Workout]) -> WeightUnit {
+    let (mut kilograms, mut pounds) = (0, 0);
+    for set in workouts.iter().flat_map(|w| &w.sets) {
+        match set.load.map(|load| load.unit()) {
+            Some(Unit::Weight(WeightUnit::Kilogram)) => kilograms += 1,
+            Some(Unit::Weight(WeightUnit::Pound)) => pounds += 1,
+            _ => {}
+        }
+    }
+
+    if pounds > kilograms {
+        WeightUnit::Pound
+    } else {
+        WeightUnit::Kilogram
+    }
+}
+
+fn volume_of<'a>(
+    volumes: &'a mut Vec<(String, Volume)>,
+    name: &str,
+    unit: WeightUnit,
+) -> &'a mut Volume {
+    let index = match volumes.iter().position(|(n, _)| n == name) {
+        Some(index) => index,
+        None => {
+            volumes.push((name.to_owned(), Volume::new(unit)));
+            volumes.len() - 1
+        }
+    };
+    &mut volumes[index].1
+}
+
+fn seconds(duration: Duration) -> Quantity {
+    Quantity::new(duration.as_secs_f64(), Unit::Time(TimeUnit::Second))
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "volume: {}", self.volume)?;
+        writeln!(
+            f,
+            "time under tension: {:#}",
+            seconds(self.time_under_tension)
+        )?;
+        writeln!(f, "rest: {:#}", seconds(self.rest))?;
+        if let Some(ratio) = self.work_rest_ratio() {
+            writeln!(f, "work:rest: {ratio:.2}")?;
+        }
+
+        for (title, volumes) in [
+            ("exercises", &self.exercises),
+            ("categories", &self.categories),
+        ] {
+            if !volumes.is_empty() {
+                writeln!(f, "{title}:")?;
+                for (name, volume) in volumes {
+                    writeln!(f, "  {name}: {volume}")?;
+                }
+            }
+        }
+
+        writeln!(f, "intensity:")?;
+        for (zone, sets) in INTENSITY_ZONES.iter().zip(self.intensity) {
+            writeln!(f, "  {zone}: {sets} sets")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{run, run_program, Options};
+
+    use super::*;
+
+    fn kilograms(value: f64) -> Quantity {
+        Quantity::new(value, Unit::Weight(WeightUnit::Kilogram))
+    }
+
+    #[test]
+    fn workout_stats() {
+        let src = r#"
+            exercise squat { category: "legs" }
+            exercise lunge { category: "legs" }
+            exercise plank {}
+            squat(3x5 @ 100kg @ T3110);
+            rest(2m);
+            squat(2x5 @ 70%);
+            lunge(2x8-12 @ 20kg);
+            plank(60s);
+            rest(1m);
+        "#;
+        let workout = run(src, &Options::default()).unwrap();
+        let profile = LifterProfile::new().with_max("squat", kilograms(125.0));
+        let stats = Stats::of(&workout, &profile);
+
+        assert_eq!(stats.volume.sets, 8);
+        assert_eq!(stats.volume.reps, 41);
+        assert_eq!(stats.volume.tonnage, kilograms(1820.0));
+        assert_eq!(stats.time_under_tension, Duration::from_secs(135));
+        assert_eq!(stats.rest, Duration::from_secs(180));
+        assert_eq!(stats.work_rest_ratio(), Some(0.75));
+
+        assert_eq!(stats.exercises.len(), 3);
+        assert_eq!(stats.exercises[0].0, "squat");
+        assert_eq!(stats.exercises[0].1.sets, 5);
+        assert_eq!(stats.exercises[0].1.tonnage, kilograms(1500.0));
+        assert_eq!(stats.categories.len(), 1);
+        assert_eq!(stats.categories[0].1.reps, 41);
+
+        // 100kg of a 125kg max is 80%
+        assert_eq!(stats.intensity, [0, 0, 2, 3, 0]);
+    }
+
+    #[test]
+    fn weekly_stats() {
+        let src = r#"
+            exercise bench { category: "chest" }
+            program "Block" {
+                week 1..=2 {
+                    day "A" { bench(3x5 @ 60kg + week * 5kg); }
+                    day "B" { bench(2x10 @ 50kg); }
+                }
+            }
+        "#;
+        let program = run_program(src, &Options::default()).unwrap();
+        let weeks = weekly(&program, &LifterProfile::new());
+
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[1].0, 2);
+        assert_eq!(weeks[1].1.volume.sets, 5);
+        assert_eq!(weeks[0].1.categories[0].1.tonnage, kilograms(1975.0));
+        assert_eq!(weeks[1].1.categories[0].1.tonnage, kilograms(2050.0));
+        assert_eq!(weeks[0].1.work_rest_ratio(), None);
+    }
+
+    #[test]
+    fn tonnage_units() {
+        let src = "exercise squat {} squat(3x5 @ 225lb); squat(1x5 @ 100kg);";
+        let stats = Stats::of(
+            &run(src, &Options::default()).unwrap(),
+            &LifterProfile::new(),
+        );
+
+        assert_eq!(stats.unit, WeightUnit::Pound);
+        assert_eq!(stats.volume.tonnage.unit(), Unit::Weight(WeightUnit::Pound));
+        assert!(stats.to_string().contains("4 sets, 20 reps, 4477lb"));
+
+        let src = "exercise squat {} squat(3x5 @ 100kg); squat(1x5 @ 100lb);";
+        let stats = Stats::of(
+            &run(src, &Options::default()).unwrap(),
+            &LifterProfile::new(),
+        );
+
+        assert_eq!(stats.unit, WeightUnit::Kilogram);
+        let kg = Unit::Weight(WeightUnit::Kilogram);
+        assert_eq!(stats.volume.tonnage.unit(), kg);
+        assert_eq!(stats.exercises[0].1.tonnage.unit(), kg);
+        assert!((stats.volume.tonnage.value() - 1726.796).abs() < 0.001);
+        assert!(stats.to_string().contains("4 sets, 20 reps, 1727kg"));
+    }
+}
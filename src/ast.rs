@@ -1,7 +1,10 @@
-use std::{fmt::Display, str::FromStr};
+use std::{cmp::Ordering, fmt::Display, rc::Rc, str::FromStr, time::Duration};
 
 use crate::{
+    exercise::Exercise,
+    function::Function,
     lexer::{Token, TokenLiteral, TokenType},
+    workout::{Structure, Work},
     WmdError,
 };
 
@@ -11,6 +14,15 @@ pub enum TimeUnit {
     Minute,
 }
 
+impl TimeUnit {
+    pub fn seconds(&self) -> f64 {
+        match self {
+            TimeUnit::Second => 1.0,
+            TimeUnit::Minute => 60.0,
+        }
+    }
+}
+
 impl Display for TimeUnit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -26,7 +38,113 @@ impl FromStr for TimeUnit {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "s" => Ok(TimeUnit::Second),
-            "m" => Ok(TimeUnit::Minute),
+            "m" | "min" => Ok(TimeUnit::Minute),
+            _ => Err(WmdError::BadUnit),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightUnit {
+    Kilogram,
+    Pound,
+}
+
+impl WeightUnit {
+    pub fn kilograms(&self) -> f64 {
+        match self {
+            WeightUnit::Kilogram => 1.0,
+            WeightUnit::Pound => 0.45359237,
+        }
+    }
+}
+
+impl Display for WeightUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeightUnit::Kilogram => write!(f, "kg"),
+            WeightUnit::Pound => write!(f, "lb"),
+        }
+    }
+}
+
+impl FromStr for WeightUnit {
+    type Err = WmdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kg" => Ok(WeightUnit::Kilogram),
+            "lb" | "lbs" => Ok(WeightUnit::Pound),
+            _ => Err(WmdError::BadUnit),
+        }
+    }
+}
+
+/// Meters are written `mtr` as `m` is already minutes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceUnit {
+    Meter,
+    Kilometer,
+    Mile,
+}
+
+impl DistanceUnit {
+    pub fn meters(&self) -> f64 {
+        match self {
+            DistanceUnit::Meter => 1.0,
+            DistanceUnit::Kilometer => 1000.0,
+            DistanceUnit::Mile => 1609.344,
+        }
+    }
+}
+
+impl Display for DistanceUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DistanceUnit::Meter => write!(f, "mtr"),
+            DistanceUnit::Kilometer => write!(f, "km"),
+            DistanceUnit::Mile => write!(f, "mi"),
+        }
+    }
+}
+
+impl FromStr for DistanceUnit {
+    type Err = WmdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mtr" => Ok(DistanceUnit::Meter),
+            "km" => Ok(DistanceUnit::Kilometer),
+            "mi" => Ok(DistanceUnit::Mile),
+            _ => Err(WmdError::BadUnit),
+        }
+    }
+}
+
+/// Effort ratings used to autoregulate training. RIR counts the reps left
+/// in reserve, so `8rpe` is the same effort as `2rir`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntensityUnit {
+    Rpe,
+    Rir,
+}
+
+impl Display for IntensityUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntensityUnit::Rpe => write!(f, "rpe"),
+            IntensityUnit::Rir => write!(f, "rir"),
+        }
+    }
+}
+
+impl FromStr for IntensityUnit {
+    type Err = WmdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rpe" => Ok(IntensityUnit::Rpe),
+            "rir" => Ok(IntensityUnit::Rir),
             _ => Err(WmdError::BadUnit),
         }
     }
@@ -37,6 +155,37 @@ pub enum Unit {
     Percent,
     Rep,
     Time(TimeUnit),
+    Weight(WeightUnit),
+    Distance(DistanceUnit),
+    /// Time taken to cover one distance unit, e.g. `4m/km`
+    Pace(TimeUnit, DistanceUnit),
+    Intensity(IntensityUnit),
+}
+
+impl Unit {
+    /// Units are compatible if a value in one can be converted to the other
+    pub fn is_compatible(&self, other: &Unit) -> bool {
+        match (self, other) {
+            (Unit::Time(_), Unit::Time(_)) => true,
+            (Unit::Weight(_), Unit::Weight(_)) => true,
+            (Unit::Distance(_), Unit::Distance(_)) => true,
+            (Unit::Pace(..), Unit::Pace(..)) => true,
+            (Unit::Intensity(_), Unit::Intensity(_)) => true,
+            (a, b) => a == b,
+        }
+    }
+
+    /// Multiplier converting a value in this unit to the base unit of
+    /// the units it is compatible with, e.g. seconds for time and kilograms for weight
+    fn base_factor(&self) -> f64 {
+        match self {
+            Unit::Percent | Unit::Rep | Unit::Intensity(_) => 1.0,
+            Unit::Time(t) => t.seconds(),
+            Unit::Weight(w) => w.kilograms(),
+            Unit::Distance(d) => d.meters(),
+            Unit::Pace(t, d) => t.seconds() / d.meters(),
+        }
+    }
 }
 
 impl Display for Unit {
@@ -45,6 +194,10 @@ impl Display for Unit {
             Unit::Percent => write!(f, "%"),
             Unit::Rep => write!(f, "x"),
             Unit::Time(t) => write!(f, "{t}"),
+            Unit::Weight(w) => write!(f, "{w}"),
+            Unit::Distance(d) => write!(f, "{d}"),
+            Unit::Pace(t, d) => write!(f, "{t}/{d}"),
+            Unit::Intensity(i) => write!(f, "{i}"),
         }
     }
 }
@@ -56,23 +209,463 @@ impl FromStr for Unit {
         match s {
             "x" => Ok(Unit::Rep),
             "%" => Ok(Unit::Percent),
-            t => Ok(Unit::Time(TimeUnit::from_str(t)?)),
+            "s" | "m" | "min" => Ok(Unit::Time(TimeUnit::from_str(s)?)),
+            "kg" | "lb" | "lbs" => Ok(Unit::Weight(WeightUnit::from_str(s)?)),
+            "mtr" | "km" | "mi" => Ok(Unit::Distance(DistanceUnit::from_str(s)?)),
+            "rpe" | "rir" => Ok(Unit::Intensity(IntensityUnit::from_str(s)?)),
+            _ => match s.split_once('/') {
+                Some((t, d)) => Ok(Unit::Pace(t.parse()?, d.parse()?)),
+                None => Err(WmdError::BadUnit),
+            },
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Quantities compare equal if they represent the same amount after
+/// conversion, so `1m == 60s`
+#[derive(Debug, Clone, Copy)]
 pub struct Quantity(f64, Unit);
 
 impl Quantity {
     pub fn new(value: f64, unit: Unit) -> Self {
         Self(value, unit)
     }
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.1
+    }
+
+    /// `None` if not a time quantity
+    pub fn to_seconds(&self) -> Option<f64> {
+        self.convert_to(Unit::Time(TimeUnit::Second))
+            .map(|q| q.value())
+    }
+
+    /// `None` if not a time quantity or if negative
+    pub fn to_duration(&self) -> Option<Duration> {
+        self.to_seconds()
+            .and_then(|s| Duration::try_from_secs_f64(s).ok())
+    }
+
+    /// `None` if unit is incompatible with the unit of self
+    pub fn convert_to(&self, unit: Unit) -> Option<Quantity> {
+        if let (Some(rpe), Unit::Intensity(intensity)) = (self.to_rpe(), unit) {
+            return match intensity {
+                IntensityUnit::Rpe => Some(Quantity(rpe, unit)),
+                IntensityUnit::Rir => Some(Quantity(10.0 - rpe, unit)),
+            };
+        }
+
+        if self.1.is_compatible(&unit) {
+            Some(Quantity(self.base_value() / unit.base_factor(), unit))
+        } else {
+            None
+        }
+    }
+
+    /// Effort as an RPE, converting from RIR. `None` if not an intensity.
+    pub fn to_rpe(&self) -> Option<f64> {
+        match self.1 {
+            Unit::Intensity(IntensityUnit::Rpe) => Some(self.0),
+            Unit::Intensity(IntensityUnit::Rir) => Some(10.0 - self.0),
+            _ => None,
+        }
+    }
+
+    fn base_value(&self) -> f64 {
+        self.0 * self.1.base_factor()
+    }
+
+    /// RPE and RIR run in opposite directions so can be compared but not
+    /// added or divided
+    fn mixes_intensities(&self, rhs: &Quantity) -> bool {
+        matches!((self.1, rhs.1), (Unit::Intensity(a), Unit::Intensity(b)) if a != b)
+    }
+
+    /// The finer of the two units, so `2m + 30s` is computed in seconds
+    pub fn finer_unit(&self, rhs: &Quantity) -> Unit {
+        if self.1.base_factor() <= rhs.1.base_factor() {
+            self.1
+        } else {
+            rhs.1
+        }
+    }
+
+    pub fn checked_add(self, rhs: Quantity) -> Option<Quantity> {
+        if !self.1.is_compatible(&rhs.1) || self.mixes_intensities(&rhs) {
+            return None;
+        }
+
+        let unit = self.finer_unit(&rhs);
+        let value = (self.base_value() + rhs.base_value()) / unit.base_factor();
+        Some(Quantity(value, unit))
+    }
+
+    pub fn checked_sub(self, rhs: Quantity) -> Option<Quantity> {
+        self.checked_add(rhs.scale(-1.0))
+    }
+
+    /// Percentages act as scalars when multiplying another quantity,
+    /// `50% * 10x` is `5x`. A pace multiplied by a distance is a time.
+    pub fn checked_mul(self, rhs: Quantity) -> Option<Quantity> {
+        match (self.1, rhs.1) {
+            (Unit::Percent, Unit::Percent) => None,
+            (Unit::Percent, _) => Some(rhs.scale(self.0 / 100.0)),
+            (_, Unit::Percent) => Some(self.scale(rhs.0 / 100.0)),
+            (Unit::Pace(t, d), Unit::Distance(_)) => {
+                let distance = rhs.convert_to(Unit::Distance(d))?;
+                Some(Quantity(self.0 * distance.0, Unit::Time(t)))
+            }
+            (Unit::Distance(_), Unit::Pace(..)) => rhs.checked_mul(self),
+            _ => None,
+        }
+    }
+
+    /// Dividing compatible quantities produces a unitless ratio. A time
+    /// divided by a distance is a pace, and by a pace is a distance.
+    pub fn checked_div(self, rhs: Quantity) -> Option<Literal> {
+        match (self.1, rhs.1) {
+            (l, r) if l.is_compatible(&r) && !self.mixes_intensities(&rhs) => {
+                Some(Literal::Number(self.base_value() / rhs.base_value()))
+            }
+            (Unit::Time(t), Unit::Distance(d)) => Some(Literal::Quantity(Quantity(
+                self.0 / rhs.0,
+                Unit::Pace(t, d),
+            ))),
+            (Unit::Time(_), Unit::Pace(t, d)) => {
+                let time = self.convert_to(Unit::Time(t))?;
+                Some(Literal::Quantity(Quantity(
+                    time.0 / rhs.0,
+                    Unit::Distance(d),
+                )))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn scale(self, factor: f64) -> Quantity {
+        Quantity(self.0 * factor, self.1)
+    }
+
+    /// Orders quantities of compatible units, `None` if they are incompatible.
+    /// Intensities are compared as RPEs, so `8rpe == 2rir`.
+    pub fn compare(&self, rhs: &Quantity) -> Option<Ordering> {
+        if let (Some(lhs), Some(rhs)) = (self.to_rpe(), rhs.to_rpe()) {
+            lhs.partial_cmp(&rhs)
+        } else if self.1.is_compatible(&rhs.1) {
+            self.base_value().partial_cmp(&rhs.base_value())
+        } else {
+            None
+        }
+    }
+}
+
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        self.compare(other) == Some(Ordering::Equal)
+    }
 }
 
+impl PartialOrd for Quantity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.compare(other)
+    }
+}
+
+/// The alternate flag `{:#}` renders times and paces in clock format,
+/// so `90s` is displayed as `1:30`
 impl Display for Quantity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", self.0, self.1)
+        match self.1 {
+            Unit::Time(t) if f.alternate() => write_clock(f, self.0 * t.seconds()),
+            Unit::Pace(t, d) if f.alternate() => {
+                write_clock(f, self.0 * t.seconds())?;
+                write!(f, "/{d}")
+            }
+            unit => write!(f, "{}{unit}", self.0),
+        }
+    }
+}
+
+fn write_clock(f: &mut std::fmt::Formatter<'_>, seconds: f64) -> std::fmt::Result {
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let total = seconds.abs().round() as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+
+    if hours > 0 {
+        write!(f, "{sign}{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        write!(f, "{sign}{minutes}:{seconds:02}")
+    }
+}
+
+/// Range of plain numbers or of quantities such as `60s..90s`. Quantity
+/// endpoints are stored in a single unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: f64,
+    pub end: f64,
+    pub inclusive: bool,
+    pub unit: Option<Unit>,
+}
+
+impl Range {
+    /// `None` if the endpoints aren't both numbers or compatible quantities
+    pub fn new(start: &Literal, end: &Literal, inclusive: bool) -> Option<Range> {
+        let (start, end, unit) = match (start, end) {
+            (Literal::Number(s), Literal::Number(e)) => (*s, *e, None),
+            (Literal::Quantity(s), Literal::Quantity(e)) => {
+                let unit = s.finer_unit(e);
+                let start = s.convert_to(unit)?.value();
+                let end = e.convert_to(unit)?.value();
+                (start, end, Some(unit))
+            }
+            _ => return None,
+        };
+
+        Some(Range {
+            start,
+            end,
+            inclusive,
+            unit,
+        })
+    }
+
+    /// Value in the unit of the range, `None` if incompatible
+    fn value_of(&self, value: &Literal) -> Option<f64> {
+        match (value, self.unit) {
+            (Literal::Number(n), None) => Some(*n),
+            (Literal::Quantity(q), Some(unit)) => q.convert_to(unit).map(|q| q.value()),
+            _ => None,
+        }
+    }
+
+    fn literal(&self, value: f64) -> Literal {
+        match self.unit {
+            Some(unit) => Literal::Quantity(Quantity::new(value, unit)),
+            None => Literal::Number(value),
+        }
+    }
+
+    /// `None` if value is incompatible with the range
+    pub fn contains(&self, value: &Literal) -> Option<bool> {
+        let value = self.value_of(value)?;
+        let below_end = if self.inclusive {
+            value <= self.end
+        } else {
+            value < self.end
+        };
+
+        Some(self.start <= value && below_end)
+    }
+
+    /// Restricts value to lie between the endpoints, `None` if value is
+    /// incompatible with the range
+    pub fn clamp(&self, value: &Literal) -> Option<Literal> {
+        let clamped = self.value_of(value)?;
+        if clamped < self.start {
+            Some(self.literal(self.start))
+        } else if clamped > self.end {
+            Some(self.literal(self.end))
+        } else {
+            Some(value.clone())
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.start.is_finite() && self.end.is_finite()
+    }
+
+    /// Steps through the range one unit at a time. Yields nothing if an
+    /// endpoint isn't finite.
+    pub fn iter(&self) -> impl Iterator<Item = Literal> {
+        let range = *self;
+        let span = range.end - range.start;
+        let steps = if !range.is_finite() || span < 0.0 {
+            0
+        } else if range.inclusive {
+            span.floor() as u64 + 1
+        } else {
+            span.ceil() as u64
+        };
+
+        (0..steps).map(move |i| range.literal(range.start + i as f64))
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = if self.inclusive { "..=" } else { ".." };
+        write!(
+            f,
+            "{}{op}{}",
+            self.literal(self.start),
+            self.literal(self.end)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reps {
+    Fixed(u32),
+    /// Inclusive range such as `8-12`
+    Range(u32, u32),
+}
+
+impl Display for Reps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reps::Fixed(r) => write!(f, "{r}"),
+            Reps::Range(low, high) => write!(f, "{low}-{high}"),
+        }
+    }
+}
+
+/// Set scheme such as `5x5`, `3x8-12`, `4x5@80%` or `3x5@100kg@8@T3110`.
+/// Sets and reps are at least one and sets at most [`Prescription::MAX_SETS`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prescription {
+    pub sets: u32,
+    pub reps: Reps,
+    pub load: Option<Quantity>,
+    /// Target effort as an RPE or RIR
+    pub intensity: Option<Quantity>,
+    pub tempo: Option<Tempo>,
+    pub exercise: Option<Rc<Exercise>>,
+}
+
+impl Prescription {
+    /// Most sets a single prescription may ask for
+    pub const MAX_SETS: u32 = 100;
+
+    pub fn new(sets: u32, reps: Reps) -> Self {
+        Self {
+            sets,
+            reps,
+            load: None,
+            intensity: None,
+            tempo: None,
+            exercise: None,
+        }
+    }
+
+    pub fn with_load(self, load: Quantity) -> Self {
+        Self {
+            load: Some(load),
+            ..self
+        }
+    }
+
+    pub fn with_intensity(self, intensity: Quantity) -> Self {
+        Self {
+            intensity: Some(intensity),
+            ..self
+        }
+    }
+
+    pub fn with_tempo(self, tempo: Tempo) -> Self {
+        Self {
+            tempo: Some(tempo),
+            ..self
+        }
+    }
+
+    pub fn with_exercise(self, exercise: Rc<Exercise>) -> Self {
+        Self {
+            exercise: Some(exercise),
+            ..self
+        }
+    }
+
+    /// Splits into one single set prescription per set
+    pub fn expand(&self) -> Vec<Prescription> {
+        let set = Prescription {
+            sets: 1,
+            ..self.clone()
+        };
+        vec![set; self.sets as usize]
+    }
+}
+
+impl Display for Prescription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(exercise) = &self.exercise {
+            write!(f, "{exercise} ")?;
+        }
+        write!(f, "{}x{}", self.sets, self.reps)?;
+        if let Some(load) = &self.load {
+            write!(f, "@{load}")?;
+        }
+        if let Some(intensity) = &self.intensity {
+            write!(f, "@{intensity}")?;
+        }
+        if let Some(tempo) = &self.tempo {
+            write!(f, "@{tempo}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Seconds spent in each phase of a rep, written `T3110` for a three
+/// second eccentric, one second pause, one second concentric and no pause
+/// at the top. An explosive `X` phase counts as no time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tempo {
+    pub eccentric: u32,
+    pub bottom: u32,
+    pub concentric: u32,
+    pub top: u32,
+}
+
+impl Tempo {
+    pub fn new(eccentric: u32, bottom: u32, concentric: u32, top: u32) -> Self {
+        Self {
+            eccentric,
+            bottom,
+            concentric,
+            top,
+        }
+    }
+
+    /// `None` unless s is a `T` followed by four digits or `X`s
+    pub fn parse(s: &str) -> Option<Tempo> {
+        let phases = s.strip_prefix('T')?;
+        if phases.len() != 4 {
+            return None;
+        }
+
+        let phases = phases
+            .chars()
+            .map(|c| if c == 'X' { Some(0) } else { c.to_digit(10) })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Tempo::new(phases[0], phases[1], phases[2], phases[3]))
+    }
+
+    pub fn rep_seconds(&self) -> u32 {
+        self.eccentric + self.bottom + self.concentric + self.top
+    }
+
+    /// Total time under tension for a number of reps
+    pub fn time_under_tension(&self, reps: f64) -> Quantity {
+        Quantity::new(
+            self.rep_seconds() as f64 * reps,
+            Unit::Time(TimeUnit::Second),
+        )
+    }
+}
+
+impl Display for Tempo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "T{}{}{}{}",
+            self.eccentric, self.bottom, self.concentric, self.top
+        )
     }
 }
 
@@ -80,6 +673,8 @@ impl Display for Quantity {
 pub enum UnaryOp {
     Minus,
     Bang,
+    /// Prefix `@8` is an RPE
+    At,
 }
 
 impl Display for UnaryOp {
@@ -87,6 +682,7 @@ impl Display for UnaryOp {
         match self {
             UnaryOp::Minus => write!(f, "-"),
             UnaryOp::Bang => write!(f, "!"),
+            UnaryOp::At => write!(f, "@"),
         }
     }
 }
@@ -98,6 +694,7 @@ impl TryFrom<TokenType> for UnaryOp {
         match value {
             TokenType::Minus => Ok(UnaryOp::Minus),
             TokenType::Bang => Ok(UnaryOp::Bang),
+            TokenType::At => Ok(UnaryOp::At),
             t => Err(WmdError::UnexpectedTokenOp(t)),
         }
     }
@@ -115,6 +712,9 @@ pub enum BinaryOp {
     GreaterEqual,
     EqualEqual,
     BangEqual,
+    At,
+    DotDot,
+    DotDotEqual,
 }
 
 impl Display for BinaryOp {
@@ -130,6 +730,9 @@ impl Display for BinaryOp {
             BinaryOp::GreaterEqual => write!(f, ">="),
             BinaryOp::EqualEqual => write!(f, "=="),
             BinaryOp::BangEqual => write!(f, "!="),
+            BinaryOp::At => write!(f, "@"),
+            BinaryOp::DotDot => write!(f, ".."),
+            BinaryOp::DotDotEqual => write!(f, "..="),
         }
     }
 }
@@ -149,6 +752,9 @@ impl TryFrom<TokenType> for BinaryOp {
             TokenType::GreaterEqual => Ok(BinaryOp::GreaterEqual),
             TokenType::EqualEqual => Ok(BinaryOp::EqualEqual),
             TokenType::BangEqual => Ok(BinaryOp::BangEqual),
+            TokenType::At => Ok(BinaryOp::At),
+            TokenType::DotDot => Ok(BinaryOp::DotDot),
+            TokenType::DotDotEqual => Ok(BinaryOp::DotDotEqual),
             t => Err(WmdError::UnexpectedTokenOp(t)),
         }
     }
@@ -229,6 +835,52 @@ pub enum Stmt {
     None,
     Expr(Expr),
     Let(IdentToken, Expr),
+    While(Expr, Expr),
+    For(IdentToken, Expr, Expr),
+    Break(usize),
+    Continue(usize),
+    Fn(Rc<FnDecl>),
+    Return(usize, Expr),
+    Exercise(ExerciseDecl),
+    Program(ProgramDecl),
+}
+
+/// `program "name" { week 1..=3 { day "name" { ... } } }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramDecl {
+    pub name: String,
+    pub weeks: Vec<WeekDecl>,
+}
+
+/// Days repeated for each week number the expression evaluates to
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeekDecl {
+    pub line: usize,
+    pub weeks: Expr,
+    pub days: Vec<DayDecl>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayDecl {
+    pub name: String,
+    pub body: Expr,
+}
+
+/// `exercise squat { name: "Back Squat", category: "legs" }`, fields are
+/// checked when the declaration is executed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExerciseDecl {
+    pub ident: IdentToken,
+    pub fields: Vec<(IdentToken, Expr)>,
+}
+
+/// Shared by named declarations and anonymous fn expressions. Body is
+/// always a block so its tail expression is the implicit return value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnDecl {
+    pub name: Option<IdentToken>,
+    pub params: Vec<IdentToken>,
+    pub body: Expr,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -240,7 +892,25 @@ pub enum Expr {
     List(Vec<Expr>),
     Literal(Literal),
     Var(IdentToken),
+    Assign(IdentToken, Box<Expr>),
     Block(Vec<Stmt>),
+    If(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+    /// Callee, line, positional arguments and named arguments such as
+    /// `bar: 20kg`, which follow the positional ones
+    Call(Box<Expr>, usize, Vec<Expr>, Vec<(IdentToken, Expr)>),
+    Lambda(Rc<FnDecl>),
+    /// Keyword, line, argument such as the rounds of a circuit, and body
+    Structure(StructureType, usize, Option<Box<Expr>>, Box<Expr>),
+}
+
+/// Keyword introducing a workout structure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructureType {
+    Superset,
+    Circuit,
+    Emom,
+    Amrap,
+    Tabata,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -249,8 +919,16 @@ pub enum Literal {
     Bool(bool),
     Number(f64),
     Quantity(Quantity),
+    Prescription(Prescription),
+    Range(Range),
+    Tempo(Tempo),
+    Exercise(Rc<Exercise>),
+    Work(Work),
+    Rest(Quantity),
+    Structure(Rc<Structure>),
     String(String),
     List(Vec<Literal>),
+    Function(Function),
 }
 
 impl Literal {
@@ -278,15 +956,23 @@ impl Display for Literal {
             Literal::Bool(b) => write!(f, "{b}"),
             Literal::Number(n) => write!(f, "{n}"),
             Literal::Quantity(q) => write!(f, "{q}"),
+            Literal::Prescription(p) => write!(f, "{p}"),
+            Literal::Range(r) => write!(f, "{r}"),
+            Literal::Tempo(t) => write!(f, "{t}"),
+            Literal::Exercise(e) => write!(f, "<exercise {e}>"),
+            Literal::Work(w) => write!(f, "{w}"),
+            Literal::Rest(r) => write!(f, "rest {r}"),
+            Literal::Structure(s) => write!(f, "{s}"),
             Literal::String(s) => write!(f, "\"{s}\""),
             Literal::List(l) => {
                 let lits = l
-                    .into_iter()
+                    .iter()
                     .map(|e| e.to_string())
                     .collect::<Vec<_>>()
                     .join(", ");
                 write!(f, "[{lits}]")
             }
+            Literal::Function(func) => write!(f, "{func}"),
         }
     }
 }
@@ -297,6 +983,8 @@ impl From<TokenLiteral> for Literal {
             TokenLiteral::String(s) => Literal::String(s),
             TokenLiteral::Number(n) => Literal::Number(n),
             TokenLiteral::Quantity(q) => Literal::Quantity(q),
+            TokenLiteral::Prescription(p) => Literal::Prescription(p),
+            TokenLiteral::Tempo(t) => Literal::Tempo(t),
         }
     }
 }
@@ -326,7 +1014,81 @@ pub trait ExprVisitor<T> {
 
     fn visit_var(&mut self, ident: &IdentToken) -> Result<T, WmdError>;
 
+    fn visit_assign(&mut self, ident: &IdentToken, value: &Expr) -> Result<T, WmdError>;
+
     fn visit_block(&mut self, stmts: &[Stmt]) -> Result<T, WmdError>;
+
+    fn visit_if(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: Option<&Expr>,
+    ) -> Result<T, WmdError>;
+
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        line: usize,
+        args: &[Expr],
+        named: &[(IdentToken, Expr)],
+    ) -> Result<T, WmdError>;
+
+    fn visit_lambda(&mut self, decl: &Rc<FnDecl>) -> Result<T, WmdError>;
+
+    fn visit_structure(
+        &mut self,
+        typ: StructureType,
+        line: usize,
+        arg: Option<&Expr>,
+        body: &Expr,
+    ) -> Result<T, WmdError>;
+}
+
+pub trait StmtVisitor<T> {
+    fn visit_expr_stmt(&mut self, expr: &Expr) -> Result<T, WmdError>;
+
+    fn visit_let(&mut self, ident: &IdentToken, initializer: &Expr) -> Result<T, WmdError>;
+
+    fn visit_while(&mut self, condition: &Expr, body: &Expr) -> Result<T, WmdError>;
+
+    fn visit_for(
+        &mut self,
+        ident: &IdentToken,
+        iterable: &Expr,
+        body: &Expr,
+    ) -> Result<T, WmdError>;
+
+    fn visit_break(&mut self, line: usize) -> Result<T, WmdError>;
+
+    fn visit_continue(&mut self, line: usize) -> Result<T, WmdError>;
+
+    fn visit_fn(&mut self, decl: &Rc<FnDecl>) -> Result<T, WmdError>;
+
+    fn visit_return(&mut self, line: usize, value: &Expr) -> Result<T, WmdError>;
+
+    fn visit_exercise(&mut self, decl: &ExerciseDecl) -> Result<T, WmdError>;
+
+    fn visit_program(&mut self, decl: &ProgramDecl) -> Result<T, WmdError>;
+
+    fn visit_none(&mut self) -> Result<T, WmdError>;
+}
+
+impl Stmt {
+    pub fn accept<T, V: StmtVisitor<T>>(&self, visitor: &mut V) -> Result<T, WmdError> {
+        match self {
+            Stmt::None => visitor.visit_none(),
+            Stmt::Expr(e) => visitor.visit_expr_stmt(e),
+            Stmt::Let(i, e) => visitor.visit_let(i, e),
+            Stmt::While(c, b) => visitor.visit_while(c, b),
+            Stmt::For(i, e, b) => visitor.visit_for(i, e, b),
+            Stmt::Break(l) => visitor.visit_break(*l),
+            Stmt::Continue(l) => visitor.visit_continue(*l),
+            Stmt::Fn(d) => visitor.visit_fn(d),
+            Stmt::Return(l, v) => visitor.visit_return(*l, v),
+            Stmt::Exercise(d) => visitor.visit_exercise(d),
+            Stmt::Program(d) => visitor.visit_program(d),
+        }
+    }
 }
 
 impl Expr {
@@ -339,7 +1101,113 @@ impl Expr {
             Expr::List(l) => visitor.visit_list(l),
             Expr::Literal(l) => visitor.visit_literal(l),
             Expr::Var(v) => visitor.visit_var(v),
+            Expr::Assign(i, v) => visitor.visit_assign(i, v),
             Expr::Block(b) => visitor.visit_block(b),
+            Expr::If(c, t, e) => visitor.visit_if(c, t, e.as_deref()),
+            Expr::Call(c, l, a, n) => visitor.visit_call(c, *l, a, n),
+            Expr::Lambda(d) => visitor.visit_lambda(d),
+            Expr::Structure(t, l, a, b) => visitor.visit_structure(*t, *l, a.as_deref(), b),
         }
     }
+
+    /// Block like expressions end in '}' so don't require a ';' to
+    /// terminate an expression stmt
+    pub fn is_block_like(&self) -> bool {
+        matches!(self, Expr::Block(_) | Expr::If(..) | Expr::Structure(..))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantity_conversions() {
+        let two_mins = Quantity::new(2.0, Unit::Time(TimeUnit::Minute));
+        assert_eq!(two_mins.value(), 2.0);
+        assert_eq!(two_mins.to_seconds(), Some(120.0));
+        assert_eq!(two_mins.to_duration(), Some(Duration::from_secs(120)));
+        assert_eq!(
+            two_mins.convert_to(Unit::Time(TimeUnit::Second)),
+            Some(Quantity::new(120.0, Unit::Time(TimeUnit::Second)))
+        );
+
+        let reps = Quantity::new(5.0, Unit::Rep);
+        assert_eq!(reps.to_seconds(), None);
+        assert_eq!(reps.convert_to(Unit::Percent), None);
+        assert_eq!(
+            Quantity::new(-1.0, Unit::Time(TimeUnit::Second)).to_duration(),
+            None
+        );
+    }
+
+    #[test]
+    fn quantity_ordering() {
+        let secs = |v| Quantity::new(v, Unit::Time(TimeUnit::Second));
+        let mins = |v| Quantity::new(v, Unit::Time(TimeUnit::Minute));
+
+        assert!(mins(1.0) > secs(59.0));
+        assert!(mins(1.0) == secs(60.0));
+        assert_eq!(Quantity::new(1.0, Unit::Rep).partial_cmp(&secs(1.0)), None);
+    }
+
+    #[test]
+    fn weight_conversions() {
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+        let lb = |v| Quantity::new(v, Unit::Weight(WeightUnit::Pound));
+
+        let converted = kg(100.0)
+            .convert_to(Unit::Weight(WeightUnit::Pound))
+            .unwrap();
+        assert!((converted.value() - 220.462).abs() < 0.001);
+        assert!(kg(100.0) > lb(220.0));
+        assert_eq!(kg(20.0).to_string(), "20kg");
+        assert_eq!(
+            "lbs".parse::<Unit>().unwrap(),
+            Unit::Weight(WeightUnit::Pound)
+        );
+    }
+
+    #[test]
+    fn distance_and_pace() {
+        let km = Unit::Distance(DistanceUnit::Kilometer);
+        let pace = Unit::Pace(TimeUnit::Minute, DistanceUnit::Kilometer);
+
+        assert_eq!("m/km".parse::<Unit>().unwrap(), pace);
+        assert!("x/km".parse::<Unit>().is_err());
+        assert_eq!(Quantity::new(4.0, pace).to_string(), "4m/km");
+
+        let run = Quantity::new(20.0, Unit::Time(TimeUnit::Minute));
+        assert_eq!(
+            run.checked_div(Quantity::new(5.0, km)),
+            Some(Literal::Quantity(Quantity::new(4.0, pace)))
+        );
+
+        let lap = Quantity::new(400.0, Unit::Distance(DistanceUnit::Meter));
+        assert_eq!(
+            Quantity::new(4.0, pace).checked_mul(lap),
+            Some(Quantity::new(96.0, Unit::Time(TimeUnit::Second)))
+        );
+
+        let mile = Quantity::new(1.0, Unit::Distance(DistanceUnit::Mile));
+        assert_eq!(mile.convert_to(km).map(|q| q.value()), Some(1.609344));
+    }
+
+    #[test]
+    fn clock_display() {
+        let secs = |v| Quantity::new(v, Unit::Time(TimeUnit::Second));
+
+        assert_eq!(format!("{:#}", secs(90.0)), "1:30");
+        assert_eq!(format!("{:#}", secs(3735.0)), "1:02:15");
+        assert_eq!(format!("{:#}", secs(-5.0)), "-0:05");
+        assert_eq!(
+            format!("{:#}", Quantity::new(2.5, Unit::Time(TimeUnit::Minute))),
+            "2:30"
+        );
+        assert_eq!(format!("{}", secs(90.0)), "90s");
+
+        let pace = Quantity::new(270.0, Unit::Pace(TimeUnit::Second, DistanceUnit::Kilometer));
+        assert_eq!(format!("{pace:#}"), "4:30/km");
+        assert_eq!(format!("{:#}", Quantity::new(5.0, Unit::Rep)), "5x");
+    }
 }
@@ -0,0 +1,299 @@
+use crate::{
+    ast::{IntensityUnit, Literal, Quantity, Range, Tempo, Unit, WeightUnit},
+    estimate::Formula,
+    function::{Arity, Callable, Function},
+    interpreter::Interpreter,
+    plates::PlateInventory,
+    WmdError,
+};
+
+type BuiltinFn = fn(&mut Interpreter, &[Literal]) -> Result<Literal, WmdError>;
+
+/// Native function that needs access to interpreter state such as the
+/// rpe table
+struct Builtin {
+    name: &'static str,
+    arity: Arity,
+    /// Names arguments can be passed by, in order
+    params: &'static [&'static str],
+    func: BuiltinFn,
+}
+
+impl Callable for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn param_index(&self, name: &str) -> Option<usize> {
+        self.params.iter().position(|p| *p == name)
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, WmdError> {
+        (self.func)(interpreter, &args)
+    }
+}
+
+fn define(
+    interpreter: &mut Interpreter,
+    name: &'static str,
+    arity: impl Into<Arity>,
+    func: BuiltinFn,
+) {
+    define_with_params(interpreter, name, arity, &[], func)
+}
+
+fn define_with_params(
+    interpreter: &mut Interpreter,
+    name: &'static str,
+    arity: impl Into<Arity>,
+    params: &'static [&'static str],
+    func: BuiltinFn,
+) {
+    let builtin = Builtin {
+        name,
+        arity: arity.into(),
+        params,
+        func,
+    };
+    interpreter.define_global(name, Literal::Function(Function::new(builtin)));
+}
+
+/// Registers the functions every wmd program has access to
+pub(crate) fn register(interpreter: &mut Interpreter) {
+    interpreter.register_native("value", 1, |args| {
+        let quantity = quantity_arg("value", &args[0])?;
+        Ok(Literal::Number(quantity.value()))
+    });
+
+    interpreter.register_native("secs", 1, |args| {
+        let quantity = quantity_arg("secs", &args[0])?;
+        quantity
+            .to_seconds()
+            .map(Literal::Number)
+            .ok_or_else(|| WmdError::Native(format!("secs expects a time, found '{quantity}'")))
+    });
+
+    interpreter.register_native("sets", 1, |args| match &args[0] {
+        Literal::Prescription(p) => Ok(Literal::List(
+            p.expand().into_iter().map(Literal::Prescription).collect(),
+        )),
+        lit => Err(WmdError::Native(format!(
+            "sets expects sets and reps, found '{lit}'"
+        ))),
+    });
+
+    interpreter.register_native("tempo", 4, |args| {
+        let phases = args
+            .iter()
+            .map(|arg| match arg {
+                Literal::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as u32),
+                lit => Err(WmdError::Native(format!(
+                    "tempo expects whole seconds, found '{lit}'"
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Literal::Tempo(Tempo::new(
+            phases[0], phases[1], phases[2], phases[3],
+        )))
+    });
+
+    interpreter.register_native("rest", 1, |args| match &args[0] {
+        Literal::Quantity(q) if q.to_duration().is_some() => Ok(Literal::Rest(*q)),
+        lit => Err(WmdError::Native(format!(
+            "rest expects a duration, found '{lit}'"
+        ))),
+    });
+
+    interpreter.register_native("contains", 2, |args| {
+        let range = range_arg("contains", &args[0])?;
+        range
+            .contains(&args[1])
+            .map(Literal::Bool)
+            .ok_or_else(|| incompatible_with_range("contains", &range, &args[1]))
+    });
+
+    interpreter.register_native("clamp", 2, |args| {
+        let range = range_arg("clamp", &args[1])?;
+        if range.start > range.end {
+            return Err(WmdError::Native(format!(
+                "clamp expects a range that doesn't end before it starts, found '{range}'"
+            )));
+        }
+        range
+            .clamp(&args[0])
+            .ok_or_else(|| incompatible_with_range("clamp", &range, &args[0]))
+    });
+
+    define(interpreter, "workout", 1, |interpreter, args| {
+        let name = string_arg("workout", &args[0])?;
+        interpreter.workout_mut().name = Some(name);
+        Ok(Literal::Nil)
+    });
+
+    define(interpreter, "section", 1, |interpreter, args| {
+        let name = string_arg("section", &args[0])?;
+        interpreter.workout_mut().start_section(&name);
+        Ok(Literal::Nil)
+    });
+
+    define(interpreter, "note", 1, |interpreter, args| {
+        let note = string_arg("note", &args[0])?;
+        interpreter.workout_mut().notes.push(note);
+        Ok(Literal::Nil)
+    });
+
+    // plates(142.5kg, bar: 15kg)
+    define_with_params(
+        interpreter,
+        "plates",
+        Arity::Range(1, 2),
+        &["load", "bar"],
+        |interpreter, args| {
+            let load = weight_arg("plates", &args[0])?;
+            let bar = args
+                .get(1)
+                .map(|bar| weight_arg("plates", bar))
+                .transpose()?;
+
+            let per_side = match interpreter.plate_inventory() {
+                Some(inventory) => inventory.per_side(load, bar)?,
+                None if load.unit() == Unit::Weight(WeightUnit::Pound) => {
+                    PlateInventory::pounds().per_side(load, bar)?
+                }
+                None => PlateInventory::kilograms().per_side(load, bar)?,
+            };
+            Ok(Literal::List(
+                per_side.into_iter().map(Literal::Quantity).collect(),
+            ))
+        },
+    );
+
+    for formula in [Formula::Epley, Formula::Brzycki, Formula::Lombardi] {
+        interpreter.register_native(&formula.to_string(), 2, move |args| {
+            estimate(&formula.to_string(), formula, args)
+        });
+    }
+
+    define(interpreter, "e1rm", 2, |interpreter, args| {
+        estimate("e1rm", interpreter.formula(), args)
+    });
+
+    define(interpreter, "rep_max", 2, |interpreter, args| {
+        let one_rep_max = weight_arg("rep_max", &args[0])?;
+        let reps = reps_arg("rep_max", &args[1])?;
+        interpreter
+            .formula()
+            .rep_max(one_rep_max, reps)
+            .map(Literal::Quantity)
+            .ok_or_else(|| {
+                WmdError::Native(format!("rep_max can't estimate a load for {reps} reps"))
+            })
+    });
+
+    define(interpreter, "percent", 2, |interpreter, args| {
+        let reps = reps_arg("percent", &args[0])?;
+        let rpe = match &args[1] {
+            Literal::Number(n) => Some(*n),
+            Literal::Quantity(q) => q.to_rpe(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            WmdError::Native(format!(
+                "percent expects an rpe or rir, found '{}'",
+                args[1]
+            ))
+        })?;
+
+        interpreter
+            .rpe_table()
+            .percent(reps, rpe)
+            .map(|p| Literal::Quantity(Quantity::new(p, Unit::Percent)))
+            .ok_or_else(|| WmdError::Native(format!("no %1RM for {reps} reps at rpe {rpe}")))
+    });
+
+    define(interpreter, "rpe", 2, |interpreter, args| {
+        let reps = reps_arg("rpe", &args[0])?;
+        let percent = match &args[1] {
+            Literal::Quantity(q) if q.unit() == Unit::Percent => q.value(),
+            lit => {
+                return Err(WmdError::Native(format!(
+                    "rpe expects a percentage, found '{lit}'"
+                )))
+            }
+        };
+
+        interpreter
+            .rpe_table()
+            .rpe(reps, percent)
+            .map(|r| Literal::Quantity(Quantity::new(r, Unit::Intensity(IntensityUnit::Rpe))))
+            .ok_or_else(|| WmdError::Native(format!("no rpe for {reps} reps at {percent}%")))
+    });
+}
+
+/// Estimated one rep max from a load and the reps it was lifted for
+fn estimate(name: &str, formula: Formula, args: &[Literal]) -> Result<Literal, WmdError> {
+    let load = weight_arg(name, &args[0])?;
+    let reps = reps_arg(name, &args[1])?;
+    formula
+        .one_rep_max(load, reps)
+        .map(Literal::Quantity)
+        .ok_or_else(|| WmdError::Native(format!("{name} can't estimate a max from {reps} reps")))
+}
+
+fn weight_arg(name: &str, arg: &Literal) -> Result<Quantity, WmdError> {
+    match arg {
+        Literal::Quantity(q) if matches!(q.unit(), Unit::Weight(_)) => Ok(*q),
+        lit => Err(WmdError::Native(format!(
+            "{name} expects a weight, found '{lit}'"
+        ))),
+    }
+}
+
+fn string_arg(name: &str, arg: &Literal) -> Result<String, WmdError> {
+    match arg {
+        Literal::String(s) => Ok(s.clone()),
+        lit => Err(WmdError::Native(format!(
+            "{name} expects a string, found '{lit}'"
+        ))),
+    }
+}
+
+/// Rep count given as a number or a rep quantity such as `5x`
+fn reps_arg(name: &str, arg: &Literal) -> Result<f64, WmdError> {
+    match arg {
+        Literal::Number(n) => Ok(*n),
+        Literal::Quantity(q) if q.unit() == Unit::Rep => Ok(q.value()),
+        lit => Err(WmdError::Native(format!(
+            "{name} expects a number of reps, found '{lit}'"
+        ))),
+    }
+}
+
+fn range_arg(name: &str, arg: &Literal) -> Result<Range, WmdError> {
+    match arg {
+        Literal::Range(r) => Ok(*r),
+        lit => Err(WmdError::Native(format!(
+            "{name} expects a range, found '{lit}'"
+        ))),
+    }
+}
+
+fn incompatible_with_range(name: &str, range: &Range, value: &Literal) -> WmdError {
+    WmdError::Native(format!(
+        "{name} can't compare '{value}' with range '{range}'"
+    ))
+}
+
+fn quantity_arg(name: &str, arg: &Literal) -> Result<Quantity, WmdError> {
+    match arg {
+        Literal::Quantity(q) => Ok(*q),
+        lit => Err(WmdError::Native(format!(
+            "{name} expects a quantity, found '{lit}'"
+        ))),
+    }
+}
@@ -0,0 +1,56 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    ast::{IdentToken, Literal},
+    WmdError,
+};
+
+/// A single scope of variable bindings. Scopes are chained through
+/// `enclosing` so lookups walk outwards until the global scope is reached.
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Literal>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// Binds name in this scope. Redefining an existing name shadows
+    /// the previous value rather than erroring.
+    pub fn define(&mut self, name: &str, value: Literal) {
+        self.values.insert(name.to_owned(), value);
+    }
+
+    pub fn get(&self, ident: &IdentToken) -> Result<Literal, WmdError> {
+        if let Some(value) = self.values.get(&ident.ident) {
+            Ok(value.clone())
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow().get(ident)
+        } else {
+            Err(WmdError::UndefinedVariable(ident.clone()))
+        }
+    }
+
+    /// Updates the nearest existing binding of ident, walking outwards
+    /// through enclosing scopes.
+    pub fn assign(&mut self, ident: &IdentToken, value: Literal) -> Result<(), WmdError> {
+        if let Some(slot) = self.values.get_mut(&ident.ident) {
+            *slot = value;
+            Ok(())
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow_mut().assign(ident, value)
+        } else {
+            Err(WmdError::UndefinedVariable(ident.clone()))
+        }
+    }
+}
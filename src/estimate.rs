@@ -0,0 +1,85 @@
+use std::fmt::Display;
+
+use crate::ast::Quantity;
+
+/// Formulas estimating a one rep max from a load lifted for several reps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Formula {
+    /// `w * (1 + r / 30)`
+    #[default]
+    Epley,
+    /// `w * 36 / (37 - r)`, only defined below 37 reps
+    Brzycki,
+    /// `w * r^0.1`
+    Lombardi,
+}
+
+impl Formula {
+    /// Ratio of the one rep max to the load that can be lifted for reps.
+    /// All formulas agree a single rep is the one rep max.
+    fn factor(&self, reps: f64) -> Option<f64> {
+        if reps < 1.0 {
+            return None;
+        } else if reps == 1.0 {
+            return Some(1.0);
+        }
+
+        match self {
+            Formula::Epley => Some(1.0 + reps / 30.0),
+            Formula::Brzycki if reps < 37.0 => Some(36.0 / (37.0 - reps)),
+            Formula::Brzycki => None,
+            Formula::Lombardi => Some(reps.powf(0.1)),
+        }
+    }
+
+    /// `None` if reps is below one or outside the formula's range
+    pub fn one_rep_max(&self, load: Quantity, reps: f64) -> Option<Quantity> {
+        self.factor(reps).map(|factor| load.scale(factor))
+    }
+
+    /// Load that can be lifted for reps given a one rep max, the inverse
+    /// of `one_rep_max`
+    pub fn rep_max(&self, one_rep_max: Quantity, reps: f64) -> Option<Quantity> {
+        self.factor(reps)
+            .map(|factor| one_rep_max.scale(1.0 / factor))
+    }
+}
+
+impl Display for Formula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Formula::Epley => write!(f, "epley"),
+            Formula::Brzycki => write!(f, "brzycki"),
+            Formula::Lombardi => write!(f, "lombardi"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{Unit, WeightUnit};
+
+    use super::*;
+
+    #[test]
+    fn formulas() {
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+
+        assert_eq!(Formula::Epley.one_rep_max(kg(100.0), 6.0), Some(kg(120.0)));
+        assert_eq!(
+            Formula::Brzycki.one_rep_max(kg(90.0), 10.0),
+            Some(kg(120.0))
+        );
+        assert_eq!(
+            Formula::Lombardi.one_rep_max(kg(100.0), 1.0),
+            Some(kg(100.0))
+        );
+        assert_eq!(Formula::Brzycki.one_rep_max(kg(100.0), 37.0), None);
+        assert_eq!(Formula::Epley.one_rep_max(kg(100.0), 0.0), None);
+
+        assert_eq!(Formula::Epley.rep_max(kg(120.0), 6.0), Some(kg(100.0)));
+        let estimate = Formula::Lombardi.one_rep_max(kg(80.0), 8.0).unwrap();
+        let rep_max = Formula::Lombardi.rep_max(estimate, 8.0).unwrap();
+        assert!((rep_max.value() - 80.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,127 @@
+use std::{fmt::Display, rc::Rc, str::FromStr};
+
+use crate::WmdError;
+
+/// What gets recorded when an exercise is performed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Load,
+    Reps,
+    Time,
+    Distance,
+    Rpe,
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Metric::Load => write!(f, "load"),
+            Metric::Reps => write!(f, "reps"),
+            Metric::Time => write!(f, "time"),
+            Metric::Distance => write!(f, "distance"),
+            Metric::Rpe => write!(f, "rpe"),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = WmdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "load" => Ok(Metric::Load),
+            "reps" => Ok(Metric::Reps),
+            "time" => Ok(Metric::Time),
+            "distance" => Ok(Metric::Distance),
+            "rpe" => Ok(Metric::Rpe),
+            _ => Err(WmdError::UnknownMetric(s.to_owned())),
+        }
+    }
+}
+
+/// Movement declared with `exercise`. The id is the identifier it was
+/// declared with, name defaults to the id when not given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exercise {
+    pub id: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub category: Option<String>,
+    pub equipment: Vec<String>,
+    pub metrics: Vec<Metric>,
+}
+
+impl Exercise {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            name: id.to_owned(),
+            aliases: Vec::new(),
+            category: None,
+            equipment: Vec::new(),
+            metrics: Vec::new(),
+        }
+    }
+
+    /// Case insensitive match against the id, name or any alias
+    pub fn is_called(&self, name: &str) -> bool {
+        std::iter::once(&self.id)
+            .chain(std::iter::once(&self.name))
+            .chain(&self.aliases)
+            .any(|n| n.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Display for Exercise {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Every exercise declared while interpreting, in declaration order
+#[derive(Debug, Default)]
+pub struct ExerciseRegistry {
+    exercises: Vec<Rc<Exercise>>,
+}
+
+impl ExerciseRegistry {
+    /// Redeclaring an id replaces the previous exercise
+    pub fn register(&mut self, exercise: Rc<Exercise>) {
+        match self.exercises.iter_mut().find(|e| e.id == exercise.id) {
+            Some(slot) => *slot = exercise,
+            None => self.exercises.push(exercise),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<Exercise>> {
+        self.exercises.iter().find(|e| e.is_called(name)).cloned()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<Exercise>> {
+        self.exercises.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_lookup() {
+        let mut registry = ExerciseRegistry::default();
+        let mut squat = Exercise::new("squat");
+        squat.name = "Back Squat".to_owned();
+        squat.aliases = vec!["BS".to_owned()];
+        registry.register(Rc::new(squat));
+        registry.register(Rc::new(Exercise::new("bench")));
+
+        assert_eq!(registry.get("back squat").unwrap().id, "squat");
+        assert_eq!(registry.get("bs").unwrap().id, "squat");
+        assert_eq!(registry.get("bench").unwrap().name, "bench");
+        assert!(registry.get("deadlift").is_none());
+
+        registry.register(Rc::new(Exercise::new("squat")));
+        assert_eq!(registry.iter().count(), 2);
+        assert!(registry.get("bs").is_none());
+    }
+}
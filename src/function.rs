@@ -0,0 +1,175 @@
+use std::{cell::RefCell, fmt::Debug, fmt::Display, rc::Rc};
+
+use crate::{
+    ast::{FnDecl, Literal},
+    environment::Environment,
+    interpreter::Interpreter,
+    WmdError,
+};
+
+/// Number of arguments a callable accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    /// Inclusive, trailing arguments are optional
+    Range(usize, usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, args: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => args == n,
+            Arity::Range(min, max) => (min..=max).contains(&args),
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(n: usize) -> Self {
+        Arity::Exact(n)
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{n}"),
+            Arity::Range(min, max) => write!(f, "{min} to {max}"),
+        }
+    }
+}
+
+/// Anything that can be invoked with call syntax `f(a, b)`
+pub trait Callable {
+    fn name(&self) -> &str;
+
+    fn arity(&self) -> Arity;
+
+    /// Position of the parameter a named argument such as `bar: 20kg`
+    /// is passed as, `None` if there is no such parameter
+    fn param_index(&self, _name: &str) -> Option<usize> {
+        None
+    }
+
+    /// Arguments have already been checked against arity
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, WmdError>;
+}
+
+/// Function value shared between bindings. Two functions are only equal
+/// if they are the same instance.
+#[derive(Clone)]
+pub struct Function(Rc<dyn Callable>);
+
+impl Function {
+    pub fn new<C: Callable + 'static>(callable: C) -> Self {
+        Self(Rc::new(callable))
+    }
+}
+
+impl std::ops::Deref for Function {
+    type Target = dyn Callable;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(Rc::as_ptr(&self.0), Rc::as_ptr(&other.0))
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Function({})", self.name())
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
+
+/// Function declared in wmd source, closing over the environment it
+/// was declared in
+pub struct UserFunction {
+    decl: Rc<FnDecl>,
+    closure: Rc<RefCell<Environment>>,
+}
+
+impl UserFunction {
+    pub fn new(decl: Rc<FnDecl>, closure: Rc<RefCell<Environment>>) -> Self {
+        Self { decl, closure }
+    }
+}
+
+impl Callable for UserFunction {
+    fn name(&self) -> &str {
+        self.decl
+            .name
+            .as_ref()
+            .map(|n| n.ident.as_str())
+            .unwrap_or("anonymous")
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Exact(self.decl.params.len())
+    }
+
+    fn param_index(&self, name: &str) -> Option<usize> {
+        self.decl.params.iter().position(|p| p.ident == name)
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, WmdError> {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        for (param, arg) in self.decl.params.iter().zip(args) {
+            environment.define(&param.ident, arg);
+        }
+
+        // Only the returned value is work, not what the body discards
+        match interpreter.scoped(environment, |i| {
+            i.discarding(|i| i.evaluate(&self.decl.body))
+        }) {
+            Err(WmdError::Return(_, value)) => Ok(value),
+            result => result,
+        }
+    }
+}
+
+type NativeFn = dyn Fn(&[Literal]) -> Result<Literal, WmdError>;
+
+/// Function implemented by the host application in rust
+pub struct NativeFunction {
+    name: String,
+    arity: Arity,
+    func: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: impl Into<Arity>, func: F) -> Self
+    where
+        F: Fn(&[Literal]) -> Result<Literal, WmdError> + 'static,
+    {
+        Self {
+            name: name.to_owned(),
+            arity: arity.into(),
+            func: Box::new(func),
+        }
+    }
+}
+
+impl Callable for NativeFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, WmdError> {
+        (self.func)(&args)
+    }
+}
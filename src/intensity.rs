@@ -0,0 +1,78 @@
+/// Converts between RPE and percentage of one rep max. Entry `i` is the
+/// %1RM that can be lifted for `i + 1` reps at RPE 10, so a set of 5 at
+/// RPE 8 reads the entry for 7 reps. Fractional efforts such as RPE 8.5
+/// are interpolated between entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpeTable {
+    percentages: Vec<f64>,
+}
+
+impl RpeTable {
+    /// Percentages should decrease as the reps increase
+    pub fn new(percentages: Vec<f64>) -> Self {
+        Self { percentages }
+    }
+
+    /// `None` if the effort is above 10 or the set falls outside the table
+    pub fn percent(&self, reps: f64, rpe: f64) -> Option<f64> {
+        if rpe > 10.0 {
+            return None;
+        }
+
+        let index = reps + (10.0 - rpe) - 1.0;
+        if index < 0.0 || index > self.percentages.len() as f64 - 1.0 {
+            return None;
+        }
+
+        let (low, high) = (index.floor() as usize, index.ceil() as usize);
+        let (a, b) = (self.percentages[low], self.percentages[high]);
+        Some(a + (b - a) * index.fract())
+    }
+
+    /// Inverse of `percent`, `None` if the load can't be lifted for reps
+    /// or is too light to be covered by the table
+    pub fn rpe(&self, reps: f64, percent: f64) -> Option<f64> {
+        let index = if self.percentages.len() == 1 && self.percentages[0] == percent {
+            0.0
+        } else {
+            self.percentages
+                .windows(2)
+                .enumerate()
+                .find(|(_, w)| w[0] >= percent && percent >= w[1])
+                .map(|(i, w)| i as f64 + (w[0] - percent) / (w[0] - w[1]))?
+        };
+
+        let rpe = 10.0 - (index + 1.0 - reps);
+        (rpe <= 10.0).then_some(rpe)
+    }
+}
+
+/// The chart popularised by Reactive Training Systems
+impl Default for RpeTable {
+    fn default() -> Self {
+        Self::new(vec![
+            100.0, 95.5, 92.2, 89.2, 86.3, 83.7, 81.1, 78.6, 76.2, 73.9, 70.7, 68.0, 65.3, 62.6,
+            59.9,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpe_table() {
+        let table = RpeTable::default();
+        assert_eq!(table.percent(1.0, 10.0), Some(100.0));
+        assert_eq!(table.percent(5.0, 8.0), Some(81.1));
+        assert_eq!(table.percent(1.0, 9.5), Some(97.75));
+        assert_eq!(table.percent(1.0, 11.0), None);
+        assert_eq!(table.percent(12.0, 6.0), None);
+
+        assert_eq!(table.rpe(5.0, 81.1), Some(8.0));
+        assert_eq!(table.rpe(1.0, 97.75), Some(9.5));
+        assert_eq!(table.rpe(5.0, 95.5), None);
+        assert_eq!(table.rpe(1.0, 50.0), None);
+    }
+}
@@ -1,18 +1,421 @@
+use std::{cell::RefCell, cmp::Ordering, collections::BTreeMap, rc::Rc};
+
 use crate::{
-    ast::{BinaryOp, Expr, ExprVisitor, IdentToken, Literal, LogicalOp, OpToken, Stmt, UnaryOp},
+    ast::{
+        BinaryOp, DayDecl, ExerciseDecl, Expr, ExprVisitor, FnDecl, IdentToken, IntensityUnit,
+        Literal, LogicalOp, OpToken, Prescription, ProgramDecl, Quantity, Range, Reps, Stmt,
+        StmtVisitor, StructureType, UnaryOp, Unit,
+    },
+    builtins,
+    environment::Environment,
+    estimate::Formula,
+    exercise::{Exercise, ExerciseRegistry},
+    function::{Arity, Function, NativeFunction, UserFunction},
+    intensity::RpeTable,
+    plates::PlateInventory,
+    profile::LifterProfile,
+    workout::{Item, Program, Structure, StructureKind, Week, Work, Workout},
     WmdError,
 };
 
-pub struct Interpreter;
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    rpe_table: RpeTable,
+    exercises: ExerciseRegistry,
+    profile: LifterProfile,
+    plates: Option<PlateInventory>,
+    formula: Formula,
+    /// Work discarded by statements inside each enclosing workout structure,
+    /// or while computing a value that is then dropped, see [`Self::discarding`]
+    collectors: Vec<Vec<Item>>,
+    workout: Workout,
+    programs: Vec<Program>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let mut interpreter = Self {
+            environment: globals.clone(),
+            globals,
+            rpe_table: RpeTable::default(),
+            exercises: ExerciseRegistry::default(),
+            profile: LifterProfile::default(),
+            plates: None,
+            formula: Formula::default(),
+            collectors: Vec::new(),
+            workout: Workout::default(),
+            programs: Vec::new(),
+        };
+
+        builtins::register(&mut interpreter);
+        interpreter
+    }
+
+    /// Exposes a host function to wmd code as a global. Arguments are checked
+    /// against arity before func is called. Registering an existing name
+    /// replaces the previous binding.
+    pub fn register_native<F>(&mut self, name: &str, arity: impl Into<Arity>, func: F)
+    where
+        F: Fn(&[Literal]) -> Result<Literal, WmdError> + 'static,
+    {
+        let function = NativeFunction::new(name, arity, func);
+        self.define_global(name, Literal::Function(Function::new(function)));
+    }
+
+    pub(crate) fn define_global(&mut self, name: &str, value: Literal) {
+        self.globals.borrow_mut().define(name, value);
+    }
+
+    /// Replaces the chart used to convert between RPE and %1RM
+    pub fn set_rpe_table(&mut self, table: RpeTable) {
+        self.rpe_table = table;
+    }
+
+    pub fn rpe_table(&self) -> &RpeTable {
+        &self.rpe_table
+    }
+
+    /// Percentage loads given to an exercise, `squat(5x5 @ 80%)`, are
+    /// resolved against the maxes in profile
+    pub fn set_profile(&mut self, profile: LifterProfile) {
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> &LifterProfile {
+        &self.profile
+    }
+
+    /// Plates used by `plates(load)`. Without an inventory a standard kg
+    /// or lb gym is assumed depending on the unit of the load.
+    pub fn set_plate_inventory(&mut self, inventory: PlateInventory) {
+        self.plates = Some(inventory);
+    }
+
+    pub fn plate_inventory(&self) -> Option<&PlateInventory> {
+        self.plates.as_ref()
+    }
+
+    /// Formula used by `e1rm` and `rep_max`, Epley unless set
+    pub fn set_formula(&mut self, formula: Formula) {
+        self.formula = formula;
+    }
+
+    pub fn formula(&self) -> Formula {
+        self.formula
+    }
+
+    /// Exercises declared so far, persisting across calls to interpret
+    pub fn exercises(&self) -> &ExerciseRegistry {
+        &self.exercises
+    }
+
+    /// Workout accumulated from the work of top level stmts so far
+    pub fn workout(&self) -> &Workout {
+        &self.workout
+    }
+
+    pub(crate) fn workout_mut(&mut self) -> &mut Workout {
+        &mut self.workout
+    }
+
+    /// Returns the accumulated workout, starting a new empty one
+    pub fn take_workout(&mut self) -> Workout {
+        std::mem::take(&mut self.workout)
+    }
+
+    /// Returns the programs declared so far in declaration order
+    pub fn take_programs(&mut self) -> Vec<Program> {
+        std::mem::take(&mut self.programs)
+    }
+
+    /// Runs the body of a day as its own workout with `week` bound to the
+    /// week number
+    fn run_day(&mut self, week: u32, day: &DayDecl) -> Result<Workout, WmdError> {
+        let collectors = std::mem::take(&mut self.collectors);
+        let outer = std::mem::replace(
+            &mut self.workout,
+            Workout {
+                name: Some(day.name.clone()),
+                ..Workout::default()
+            },
+        );
+
+        let mut environment = Environment::with_enclosing(self.environment.clone());
+        environment.define("week", Literal::Number(week as f64));
+        let result = self
+            .scoped(environment, |interpreter| interpreter.evaluate(&day.body))
+            .map(|tail| self.collect(tail));
+
+        let workout = std::mem::replace(&mut self.workout, outer);
+        self.collectors = collectors;
+        result.map(|_| workout)
+    }
+
+    /// Executes each stmt in order, returning the value of the last one.
+    /// Bindings persist across calls so a repl can build on earlier lines.
+    pub fn interpret(&mut self, stmts: &[Stmt]) -> Result<Literal, WmdError> {
+        let mut value = Literal::Nil;
+        for stmt in stmts {
+            value = self.execute(stmt)?;
+            self.collect(value.clone());
+        }
+
+        Ok(value)
+    }
+
+    /// Expression stmts produce their value, all other stmts produce nil
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<Literal, WmdError> {
+        stmt.accept(self)
     }
 
     pub fn evaluate(&mut self, expr: &Expr) -> Result<Literal, WmdError> {
         expr.accept(self)
     }
+
+    /// Values of all but the tail stmt are discarded, so are collected
+    /// if they are work
+    fn execute_block(
+        &mut self,
+        stmts: &[Stmt],
+        environment: Environment,
+    ) -> Result<Literal, WmdError> {
+        self.scoped(environment, |interpreter| {
+            let Some((tail, stmts)) = stmts.split_last() else {
+                return Ok(Literal::Nil);
+            };
+
+            for stmt in stmts {
+                let value = interpreter.execute(stmt)?;
+                interpreter.collect(value);
+            }
+            interpreter.execute(tail)
+        })
+    }
+
+    /// Adds value to the innermost workout structure being evaluated, or
+    /// to the workout outside of any structure, if it is work, rest or a
+    /// structure. Lists are collected item by item so `sets(5x5)`
+    /// contributes each set.
+    ///
+    /// Only statements of the workout, a structure, a loop or a block in
+    /// their place are collected. While a value is computed for a `let` or
+    /// a function call, work its statements discard is dropped, so
+    /// `let x = { 3x5; 5x5 };` records nothing and a function contributes
+    /// only the value it returns.
+    fn collect(&mut self, value: Literal) {
+        let values = match value {
+            Literal::List(values) => values,
+            value => vec![value],
+        };
+
+        let items = values.into_iter().filter_map(|value| match value {
+            Literal::Prescription(p) => Some(Item::Sets(p)),
+            Literal::Work(w) => Some(Item::Work(w)),
+            Literal::Rest(r) => Some(Item::Rest(r)),
+            Literal::Structure(s) => Some(Item::Structure(s)),
+            _ => None,
+        });
+
+        match self.collectors.last_mut() {
+            Some(collector) => collector.extend(items),
+            None => items.for_each(|item| self.workout.record(item)),
+        }
+    }
+
+    /// Runs f with a collector that is dropped afterwards, so work discarded
+    /// while computing a value isn't recorded
+    pub(crate) fn discarding<T, F>(&mut self, f: F) -> Result<T, WmdError>
+    where
+        F: FnOnce(&mut Self) -> Result<T, WmdError>,
+    {
+        self.collectors.push(Vec::new());
+        let result = f(self);
+        self.collectors.pop();
+        result
+    }
+
+    /// Runs f with environment as the current scope, restoring the
+    /// previous scope afterwards even if f fails.
+    pub(crate) fn scoped<T, F>(&mut self, environment: Environment, f: F) -> Result<T, WmdError>
+    where
+        F: FnOnce(&mut Self) -> Result<T, WmdError>,
+    {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+
+        let result = f(self);
+        self.environment = previous;
+        result
+    }
+
+    /// Evaluates a loop body, absorbing 'break' and 'continue' signals.
+    /// Returns false if the loop should stop.
+    fn run_loop_body(&mut self, body: &Expr) -> Result<bool, WmdError> {
+        match self.evaluate(body) {
+            Ok(value) => {
+                self.collect(value);
+                Ok(true)
+            }
+            Err(WmdError::Continue(_)) => Ok(true),
+            Err(WmdError::Break(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl StmtVisitor<Literal> for Interpreter {
+    fn visit_expr_stmt(&mut self, expr: &Expr) -> Result<Literal, WmdError> {
+        self.evaluate(expr)
+    }
+
+    fn visit_let(&mut self, ident: &IdentToken, initializer: &Expr) -> Result<Literal, WmdError> {
+        let value = self.discarding(|interpreter| interpreter.evaluate(initializer))?;
+        self.environment.borrow_mut().define(&ident.ident, value);
+        Ok(Literal::Nil)
+    }
+
+    fn visit_while(&mut self, condition: &Expr, body: &Expr) -> Result<Literal, WmdError> {
+        while self.evaluate(condition)?.is_truthy() {
+            if !self.run_loop_body(body)? {
+                break;
+            }
+        }
+
+        Ok(Literal::Nil)
+    }
+
+    fn visit_for(
+        &mut self,
+        ident: &IdentToken,
+        iterable: &Expr,
+        body: &Expr,
+    ) -> Result<Literal, WmdError> {
+        // Ranges are stepped through lazily so `break` can end a long loop
+        let items: Box<dyn Iterator<Item = Literal>> = match self.evaluate(iterable)? {
+            Literal::List(items) => Box::new(items.into_iter()),
+            Literal::Prescription(p) => Box::new(p.expand().into_iter().map(Literal::Prescription)),
+            Literal::Range(r) if r.is_finite() => Box::new(r.iter()),
+            Literal::Range(r) => return Err(WmdError::UnboundedRange(ident.line, r)),
+            lit => return Err(WmdError::NotIterable(ident.line, lit)),
+        };
+
+        for item in items {
+            let mut environment = Environment::with_enclosing(self.environment.clone());
+            environment.define(&ident.ident, item);
+
+            if !self.scoped(environment, |interpreter| interpreter.run_loop_body(body))? {
+                break;
+            }
+        }
+
+        Ok(Literal::Nil)
+    }
+
+    fn visit_break(&mut self, line: usize) -> Result<Literal, WmdError> {
+        Err(WmdError::Break(line))
+    }
+
+    fn visit_continue(&mut self, line: usize) -> Result<Literal, WmdError> {
+        Err(WmdError::Continue(line))
+    }
+
+    fn visit_fn(&mut self, decl: &Rc<FnDecl>) -> Result<Literal, WmdError> {
+        let function = UserFunction::new(decl.clone(), self.environment.clone());
+        if let Some(name) = &decl.name {
+            self.environment
+                .borrow_mut()
+                .define(&name.ident, Literal::Function(Function::new(function)));
+        }
+
+        Ok(Literal::Nil)
+    }
+
+    fn visit_return(&mut self, line: usize, value: &Expr) -> Result<Literal, WmdError> {
+        let value = self.evaluate(value)?;
+        Err(WmdError::Return(line, value))
+    }
+
+    fn visit_exercise(&mut self, decl: &ExerciseDecl) -> Result<Literal, WmdError> {
+        let mut exercise = Exercise::new(&decl.ident.ident);
+
+        for (field, expr) in &decl.fields {
+            let value = self.evaluate(expr)?;
+            match field.ident.as_str() {
+                "name" => exercise.name = string_field(field, value)?,
+                "aliases" => exercise.aliases = string_list_field(field, value)?,
+                "category" => exercise.category = Some(string_field(field, value)?),
+                "equipment" => exercise.equipment = string_list_field(field, value)?,
+                "metrics" => {
+                    exercise.metrics = string_list_field(field, value)?
+                        .iter()
+                        .map(|m| m.parse())
+                        .collect::<Result<_, WmdError>>()
+                        .map_err(|e| {
+                            WmdError::ExerciseField(field.clone(), format!("is invalid: {e}"))
+                        })?
+                }
+                _ => {
+                    return Err(WmdError::ExerciseField(
+                        field.clone(),
+                        "is unknown, expected name, aliases, category, equipment or metrics"
+                            .to_owned(),
+                    ))
+                }
+            }
+        }
+
+        let exercise = Rc::new(exercise);
+        self.exercises.register(exercise.clone());
+        self.environment
+            .borrow_mut()
+            .define(&decl.ident.ident, Literal::Exercise(exercise));
+
+        Ok(Literal::Nil)
+    }
+
+    fn visit_program(&mut self, decl: &ProgramDecl) -> Result<Literal, WmdError> {
+        let mut weeks: BTreeMap<u32, Vec<Workout>> = BTreeMap::new();
+
+        for week in &decl.weeks {
+            let numbers = match self.evaluate(&week.weeks)? {
+                Literal::Range(r) if r.unit.is_none() && r.is_finite() => r.iter().collect(),
+                lit => vec![lit],
+            };
+
+            for number in numbers {
+                let number = match number {
+                    Literal::Number(n) if n >= 1.0 && n.fract() == 0.0 => n as u32,
+                    lit => return Err(WmdError::InvalidWeek(week.line, lit)),
+                };
+
+                for day in &week.days {
+                    let workout = self.run_day(number, day)?;
+                    weeks.entry(number).or_default().push(workout);
+                }
+            }
+        }
+
+        self.programs.push(Program {
+            name: decl.name.clone(),
+            weeks: weeks
+                .into_iter()
+                .map(|(number, days)| Week { number, days })
+                .collect(),
+        });
+
+        Ok(Literal::Nil)
+    }
+
+    fn visit_none(&mut self) -> Result<Literal, WmdError> {
+        Ok(Literal::Nil)
+    }
 }
 
 impl ExprVisitor<Literal> for Interpreter {
@@ -25,7 +428,7 @@ impl ExprVisitor<Literal> for Interpreter {
     }
 
     fn visit_list(&mut self, exprs: &[Expr]) -> Result<Literal, WmdError> {
-        let lits: Result<Vec<_>, _> = exprs.into_iter().map(|e| self.evaluate(e)).collect();
+        let lits: Result<Vec<_>, _> = exprs.iter().map(|e| self.evaluate(e)).collect();
         Ok(Literal::List(lits?))
     }
 
@@ -38,6 +441,33 @@ impl ExprVisitor<Literal> for Interpreter {
         let lhs = self.evaluate(lhs)?;
         let rhs = self.evaluate(rhs)?;
 
+        match op.typ {
+            BinaryOp::At => return with_load(lhs, op, rhs),
+            BinaryOp::DotDot => return range(lhs, op, rhs, false),
+            BinaryOp::DotDotEqual => return range(lhs, op, rhs, true),
+            _ => {}
+        }
+
+        match (&lhs, &rhs) {
+            (Literal::Quantity(l), Literal::Quantity(r)) => return quantity_op(*l, op, *r),
+            (Literal::Quantity(q), Literal::Number(n))
+            | (Literal::Number(n), Literal::Quantity(q))
+                if op.typ == BinaryOp::Star =>
+            {
+                return Ok(Literal::Quantity(q.scale(*n)))
+            }
+            (Literal::Quantity(q), Literal::Number(n)) if op.typ == BinaryOp::Slash => {
+                return Ok(Literal::Quantity(q.scale(1.0 / n)))
+            }
+            (Literal::Tempo(t), Literal::Quantity(q))
+            | (Literal::Quantity(q), Literal::Tempo(t))
+                if op.typ == BinaryOp::Star && q.unit() == Unit::Rep =>
+            {
+                return Ok(Literal::Quantity(t.time_under_tension(q.value())))
+            }
+            _ => {}
+        }
+
         match op.typ {
             BinaryOp::Plus => add_or_concat(lhs, op, rhs),
             BinaryOp::Minus => binary_num_op(lhs, op, rhs, |a, b| Literal::Number(a - b)),
@@ -49,6 +479,9 @@ impl ExprVisitor<Literal> for Interpreter {
             BinaryOp::GreaterEqual => binary_num_op(lhs, op, rhs, |a, b| Literal::Bool(a >= b)),
             BinaryOp::EqualEqual => Ok(Literal::Bool(lhs == rhs)),
             BinaryOp::BangEqual => Ok(Literal::Bool(lhs != rhs)),
+            BinaryOp::At | BinaryOp::DotDot | BinaryOp::DotDotEqual => {
+                unreachable!("'{}' is handled before arithmetic", op.typ)
+            }
         }
     }
 
@@ -56,11 +489,21 @@ impl ExprVisitor<Literal> for Interpreter {
         let rhs = self.evaluate(expr)?;
 
         match op.typ {
-            UnaryOp::Minus => {
-                let num = rhs.as_number().ok_or(WmdError::UnaryNumberRequired(op))?;
-                Ok(Literal::Number(-num))
-            }
+            UnaryOp::Minus => match rhs {
+                Literal::Quantity(q) => Ok(Literal::Quantity(q.scale(-1.0))),
+                rhs => {
+                    let num = rhs.as_number().ok_or(WmdError::UnaryNumberRequired(op))?;
+                    Ok(Literal::Number(-num))
+                }
+            },
             UnaryOp::Bang => Ok(Literal::Bool(!rhs.is_truthy())),
+            UnaryOp::At => {
+                let rpe = rhs.as_number().ok_or(WmdError::UnaryNumberRequired(op))?;
+                Ok(Literal::Quantity(Quantity::new(
+                    rpe,
+                    Unit::Intensity(IntensityUnit::Rpe),
+                )))
+            }
         }
     }
 
@@ -80,13 +523,162 @@ impl ExprVisitor<Literal> for Interpreter {
     }
 
     fn visit_var(&mut self, ident: &IdentToken) -> Result<Literal, WmdError> {
-        todo!()
+        self.environment.borrow().get(ident)
+    }
+
+    fn visit_assign(&mut self, ident: &IdentToken, value: &Expr) -> Result<Literal, WmdError> {
+        let value = self.evaluate(value)?;
+        self.environment.borrow_mut().assign(ident, value.clone())?;
+        Ok(value)
     }
 
-    /// Evaluate a list of stmts but return last expr
+    /// Evaluate a list of stmts in a new scope but return last expr
     fn visit_block(&mut self, stmts: &[Stmt]) -> Result<Literal, WmdError> {
-        todo!()
+        let environment = Environment::with_enclosing(self.environment.clone());
+        self.execute_block(stmts, environment)
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: Option<&Expr>,
+    ) -> Result<Literal, WmdError> {
+        if self.evaluate(condition)?.is_truthy() {
+            self.evaluate(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.evaluate(else_branch)
+        } else {
+            Ok(Literal::Nil)
+        }
+    }
+
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        line: usize,
+        args: &[Expr],
+        named: &[(IdentToken, Expr)],
+    ) -> Result<Literal, WmdError> {
+        let callee = self.evaluate(callee)?;
+        let mut args = args
+            .iter()
+            .map(|a| self.evaluate(a))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let function = match callee {
+            Literal::Function(f) => f,
+            Literal::Exercise(e) => match named.first() {
+                Some((name, _)) => {
+                    return Err(WmdError::InvalidArgument(
+                        name.clone(),
+                        format!("isn't accepted by exercise '{e}'"),
+                    ))
+                }
+                None => return perform(e, line, args, &self.profile),
+            },
+            lit => return Err(WmdError::NotCallable(line, lit)),
+        };
+
+        // Named arguments fill the parameters following the positional
+        // arguments, in any order but without gaps
+        let mut by_position = Vec::new();
+        for (name, expr) in named {
+            let index = function.param_index(&name.ident).ok_or_else(|| {
+                WmdError::InvalidArgument(
+                    name.clone(),
+                    format!("isn't a parameter of '{}'", function.name()),
+                )
+            })?;
+            by_position.push((index, name, self.evaluate(expr)?));
+        }
+        by_position.sort_by_key(|(index, ..)| *index);
+        for (index, name, value) in by_position {
+            if index < args.len() {
+                return Err(WmdError::InvalidArgument(
+                    name.clone(),
+                    "is given more than once".to_owned(),
+                ));
+            } else if index > args.len() {
+                return Err(WmdError::InvalidArgument(
+                    name.clone(),
+                    "is given without the arguments before it".to_owned(),
+                ));
+            }
+            args.push(value);
+        }
+
+        if !function.arity().accepts(args.len()) {
+            return Err(WmdError::ArityMismatch {
+                line,
+                expected: function.arity(),
+                got: args.len(),
+            });
+        }
+
+        function
+            .call(self, args)
+            .map_err(|error| match error.line() {
+                Some(_) => error,
+                None => WmdError::Call {
+                    line,
+                    error: Box::new(error),
+                },
+            })
+    }
+
+    fn visit_structure(
+        &mut self,
+        typ: StructureType,
+        line: usize,
+        arg: Option<&Expr>,
+        body: &Expr,
+    ) -> Result<Literal, WmdError> {
+        let arg = arg.map(|a| self.evaluate(a)).transpose()?;
+        let kind = match (typ, arg) {
+            (StructureType::Superset, _) => StructureKind::Superset,
+            (StructureType::Tabata, _) => StructureKind::Tabata,
+            (StructureType::Circuit, Some(Literal::Number(n)))
+                if (1.0..=StructureKind::MAX_ROUNDS as f64).contains(&n) && n.fract() == 0.0 =>
+            {
+                StructureKind::Circuit(n as u32)
+            }
+            (StructureType::Circuit, arg) => {
+                return Err(WmdError::InvalidRounds(line, arg.unwrap_or(Literal::Nil)))
+            }
+            (StructureType::Emom, Some(Literal::Quantity(q))) if within_minutes(q) => {
+                let minutes = q.to_seconds().unwrap_or_default() / 60.0;
+                if minutes < 1.0 || minutes.fract() != 0.0 {
+                    return Err(WmdError::PartialMinutes(line, q));
+                }
+                StructureKind::Emom(q)
+            }
+            (StructureType::Amrap, Some(Literal::Quantity(q))) if within_minutes(q) => {
+                StructureKind::Amrap(q)
+            }
+            (_, arg) => return Err(WmdError::InvalidDuration(line, arg.unwrap_or(Literal::Nil))),
+        };
+
+        self.collectors.push(Vec::new());
+        // The collector is popped even if the body fails
+        let result = self.evaluate(body).map(|tail| self.collect(tail));
+        let items = self.collectors.pop().unwrap_or_default();
+        result?;
+
+        Ok(Literal::Structure(Rc::new(Structure { kind, items })))
     }
+
+    fn visit_lambda(&mut self, decl: &Rc<FnDecl>) -> Result<Literal, WmdError> {
+        let function = UserFunction::new(decl.clone(), self.environment.clone());
+        Ok(Literal::Function(Function::new(function)))
+    }
+}
+
+/// Whether q is a positive duration of at most [`StructureKind::MAX_MINUTES`]
+fn within_minutes(q: Quantity) -> bool {
+    q.to_duration().is_some_and(|d| {
+        !d.is_zero() && d.as_secs_f64() <= StructureKind::MAX_MINUTES as f64 * 60.0
+    })
 }
 
 fn binary_num_op<F>(
@@ -103,6 +695,183 @@ where
     Ok(f(lhs, rhs))
 }
 
+/// Quantities combine only if their units are compatible. Equality between
+/// incompatible units is false rather than an error.
+fn quantity_op(lhs: Quantity, op: OpToken<BinaryOp>, rhs: Quantity) -> Result<Literal, WmdError> {
+    let incompatible = || WmdError::IncompatibleUnits(op, lhs.unit(), rhs.unit());
+    let compare = |f: fn(Ordering) -> bool| {
+        lhs.compare(&rhs)
+            .map(|o| Literal::Bool(f(o)))
+            .ok_or_else(incompatible)
+    };
+
+    match op.typ {
+        BinaryOp::Plus => lhs
+            .checked_add(rhs)
+            .map(Literal::Quantity)
+            .ok_or_else(incompatible),
+        BinaryOp::Minus => lhs
+            .checked_sub(rhs)
+            .map(Literal::Quantity)
+            .ok_or_else(incompatible),
+        BinaryOp::Star => lhs
+            .checked_mul(rhs)
+            .map(Literal::Quantity)
+            .ok_or_else(incompatible),
+        BinaryOp::Slash => lhs.checked_div(rhs).ok_or_else(incompatible),
+        BinaryOp::Less => compare(Ordering::is_lt),
+        BinaryOp::LessEqual => compare(Ordering::is_le),
+        BinaryOp::Greater => compare(Ordering::is_gt),
+        BinaryOp::GreaterEqual => compare(Ordering::is_ge),
+        BinaryOp::EqualEqual => Ok(Literal::Bool(lhs.compare(&rhs) == Some(Ordering::Equal))),
+        BinaryOp::BangEqual => Ok(Literal::Bool(lhs.compare(&rhs) != Some(Ordering::Equal))),
+        BinaryOp::At | BinaryOp::DotDot | BinaryOp::DotDotEqual => {
+            unreachable!("'{}' is handled before arithmetic", op.typ)
+        }
+    }
+}
+
+fn range(
+    start: Literal,
+    op: OpToken<BinaryOp>,
+    end: Literal,
+    inclusive: bool,
+) -> Result<Literal, WmdError> {
+    match (&start, &end) {
+        (Literal::Quantity(s), Literal::Quantity(e)) if !s.unit().is_compatible(&e.unit()) => {
+            Err(WmdError::IncompatibleUnits(op, s.unit(), e.unit()))
+        }
+        _ => match Range::new(&start, &end, inclusive) {
+            Some(range) if range.is_finite() => Ok(Literal::Range(range)),
+            Some(range) => Err(WmdError::UnboundedRange(op.line, range)),
+            None => Err(WmdError::BinaryNumberRequired(op)),
+        },
+    }
+}
+
+/// Whether q is a whole number of reps, at least one, such as `5x`
+fn is_rep_count(q: Quantity) -> bool {
+    q.unit() == Unit::Rep && q.value() >= 1.0 && q.value().fract() == 0.0
+}
+
+/// Attaches a load to a prescription. A bare rep count such as `5x` is
+/// treated as a single set. Each of the load, intensity and tempo can
+/// only be given once. Loads must be positive, RPEs from 1 to 10 and RIRs
+/// at least 0.
+fn with_load(lhs: Literal, op: OpToken<BinaryOp>, rhs: Literal) -> Result<Literal, WmdError> {
+    let prescription = match &lhs {
+        Literal::Prescription(p) => p.clone(),
+        Literal::Quantity(q) if is_rep_count(*q) => {
+            Prescription::new(1, Reps::Fixed(q.value() as u32))
+        }
+        _ => return Err(WmdError::InvalidLoad(op, Box::new(lhs), Box::new(rhs))),
+    };
+    let already_set =
+        |prescription, what| Err(WmdError::AlreadySet(op, Box::new(prescription), what));
+
+    // A bare number after `@` is an RPE, so `3x5 @ 100kg @ 8` sets both.
+    // Tempos can be given the same way, `3x5 @ T3110`.
+    let intensity = match rhs {
+        Literal::Quantity(load) if matches!(load.unit(), Unit::Percent | Unit::Weight(_)) => {
+            if load.value() <= 0.0 || !load.value().is_finite() {
+                return Err(WmdError::InvalidLoad(op, Box::new(lhs), Box::new(rhs)));
+            }
+            if prescription.load.is_some() {
+                return already_set(prescription, "load");
+            }
+            return Ok(Literal::Prescription(prescription.with_load(load)));
+        }
+        Literal::Tempo(tempo) => {
+            if prescription.tempo.is_some() {
+                return already_set(prescription, "tempo");
+            }
+            return Ok(Literal::Prescription(prescription.with_tempo(tempo)));
+        }
+        Literal::Quantity(intensity) if matches!(intensity.unit(), Unit::Intensity(_)) => intensity,
+        Literal::Number(rpe) => Quantity::new(rpe, Unit::Intensity(IntensityUnit::Rpe)),
+        _ => return Err(WmdError::InvalidLoad(op, Box::new(lhs), Box::new(rhs))),
+    };
+
+    let in_range = match intensity.unit() {
+        Unit::Intensity(IntensityUnit::Rpe) => (1.0..=10.0).contains(&intensity.value()),
+        _ => intensity.value() >= 0.0 && intensity.value().is_finite(),
+    };
+    if !in_range {
+        return Err(WmdError::InvalidLoad(op, Box::new(lhs), Box::new(rhs)));
+    }
+    if prescription.intensity.is_some() {
+        return already_set(prescription, "intensity");
+    }
+    Ok(Literal::Prescription(
+        prescription.with_intensity(intensity),
+    ))
+}
+
+/// Calling an exercise with sets and reps, `squat(5x5 @ 80%)`, tags them
+/// with the exercise. Calling it with a time or distance, `plank(60s)`,
+/// is work performed for that amount, which must be positive. Percentage loads are resolved to
+/// absolute loads if profile has a max for the exercise.
+fn perform(
+    exercise: Rc<Exercise>,
+    line: usize,
+    args: Vec<Literal>,
+    profile: &LifterProfile,
+) -> Result<Literal, WmdError> {
+    let [arg] = <[Literal; 1]>::try_from(args).map_err(|args| WmdError::ArityMismatch {
+        line,
+        expected: Arity::Exact(1),
+        got: args.len(),
+    })?;
+
+    let prescription = match arg {
+        Literal::Prescription(p) => p,
+        Literal::Quantity(q) if is_rep_count(q) => {
+            Prescription::new(1, Reps::Fixed(q.value() as u32))
+        }
+        Literal::Quantity(amount)
+            if matches!(amount.unit(), Unit::Time(_) | Unit::Distance(_))
+                && amount.value() > 0.0
+                && amount.value().is_finite() =>
+        {
+            return Ok(Literal::Work(Work { exercise, amount }))
+        }
+        lit => return Err(WmdError::InvalidWork(line, lit)),
+    };
+
+    let prescription = match prescription
+        .load
+        .and_then(|load| profile.resolve(&exercise, load))
+    {
+        Some(load) => prescription.with_load(load),
+        None => prescription,
+    };
+
+    Ok(Literal::Prescription(prescription.with_exercise(exercise)))
+}
+
+fn string_field(field: &IdentToken, value: Literal) -> Result<String, WmdError> {
+    match value {
+        Literal::String(s) => Ok(s),
+        lit => Err(WmdError::ExerciseField(
+            field.clone(),
+            format!("expects a string, found '{lit}'"),
+        )),
+    }
+}
+
+fn string_list_field(field: &IdentToken, value: Literal) -> Result<Vec<String>, WmdError> {
+    match value {
+        Literal::List(items) => items
+            .into_iter()
+            .map(|item| string_field(field, item))
+            .collect(),
+        lit => Err(WmdError::ExerciseField(
+            field.clone(),
+            format!("expects a list of strings, found '{lit}'"),
+        )),
+    }
+}
+
 fn add_or_concat(lhs: Literal, op: OpToken<BinaryOp>, rhs: Literal) -> Result<Literal, WmdError> {
     match (lhs, rhs) {
         (Literal::String(lhs), Literal::String(rhs)) => Ok(Literal::String(format!("{lhs}{rhs}"))),
@@ -121,3 +890,857 @@ fn add_or_concat(lhs: Literal, op: OpToken<BinaryOp>, rhs: Literal) -> Result<Li
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        ast::{DistanceUnit, TimeUnit, WeightUnit},
+        exercise::Metric,
+        lexer::Lexer,
+        parser::Parser,
+        profile::Rounding,
+        reporting::StdoutReporter,
+        workout::SegmentKind,
+    };
+
+    use super::*;
+
+    fn run(src: &str) -> Result<Literal, WmdError> {
+        run_with(&mut Interpreter::new(), src)
+    }
+
+    fn run_with(interpreter: &mut Interpreter, src: &str) -> Result<Literal, WmdError> {
+        let reporter = StdoutReporter;
+        let tokens = Lexer::new(src, &reporter).scan_tokens();
+        let stmts = Parser::new(tokens, &reporter).parse()?;
+        interpreter.interpret(&stmts)
+    }
+
+    #[test]
+    fn let_bindings() {
+        assert_eq!(
+            run("let a = 2; let b = a * 3; b;").unwrap(),
+            Literal::Number(6.0)
+        );
+        assert_eq!(run("let a; a;").unwrap(), Literal::Nil);
+        assert_eq!(
+            run("let a = 1; let a = a + 1; a;").unwrap(),
+            Literal::Number(2.0)
+        );
+    }
+
+    #[test]
+    fn undefined_variable() {
+        let err = run("let a = 1;\nb;").unwrap_err();
+        assert!(matches!(
+            err,
+            WmdError::UndefinedVariable(IdentToken { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn block_scope() {
+        let src = "let a = 1; { let a = 2; let b = 3; } a;";
+        assert_eq!(run(src).unwrap(), Literal::Number(1.0));
+        assert!(run("{ let b = 3; } b;").is_err());
+    }
+
+    #[test]
+    fn assignment() {
+        let src = "let weight = 100; { weight += 5; weight *= 2; } weight;";
+        assert_eq!(run(src).unwrap(), Literal::Number(210.0));
+        assert_eq!(
+            run("let a; let b = a = 2; b;").unwrap(),
+            Literal::Number(2.0)
+        );
+        assert!(matches!(
+            run("a = 1;").unwrap_err(),
+            WmdError::UndefinedVariable(_)
+        ));
+    }
+
+    #[test]
+    fn if_else() {
+        let src = "let rpe = 9; let load = 100;
+            if rpe > 8 { load -= 5 } else if rpe < 6 { load += 5 }
+            load;";
+        assert_eq!(run(src).unwrap(), Literal::Number(95.0));
+        assert_eq!(run("if nil { 1 }").unwrap(), Literal::Nil);
+        assert_eq!(
+            run("let a = if false { 1 } else { 2 }; a;").unwrap(),
+            Literal::Number(2.0)
+        );
+    }
+
+    #[test]
+    fn while_loop() {
+        let src = "let sets = 0; let reps = 0;
+            while true {
+                sets += 1;
+                if sets == 3 { continue; }
+                reps += 5;
+                if sets >= 5 { break; }
+            }
+            [sets, reps];";
+        assert_eq!(
+            run(src).unwrap(),
+            Literal::List(vec![Literal::Number(5.0), Literal::Number(20.0)])
+        );
+    }
+
+    #[test]
+    fn for_loop() {
+        let src = "let total = 0;
+            for reps in [5, 3, 1, 99] {
+                if reps > 5 { break; }
+                total += reps;
+            }
+            total;";
+        assert_eq!(run(src).unwrap(), Literal::Number(9.0));
+        assert!(run("for x in [1] { x; } x;").is_err());
+        assert!(matches!(
+            run("for x in 5 {}").unwrap_err(),
+            WmdError::NotIterable(1, Literal::Number(_))
+        ));
+    }
+
+    #[test]
+    fn functions() {
+        let src = "fn pyramid(top) {
+                let total = 0;
+                for reps in [top, top - 2, top - 4] {
+                    if reps <= 0 { return total; }
+                    total += reps;
+                }
+                total
+            }
+            [pyramid(4), pyramid(5)];";
+        assert_eq!(
+            run(src).unwrap(),
+            Literal::List(vec![Literal::Number(6.0), Literal::Number(9.0)])
+        );
+
+        let src = "fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } } fib(10);";
+        assert_eq!(run(src).unwrap(), Literal::Number(55.0));
+    }
+
+    #[test]
+    fn closures() {
+        let src = "fn counter() {
+                let count = 0;
+                fn() { count += 1 }
+            }
+            let next = counter();
+            next();
+            next();";
+        assert_eq!(run(src).unwrap(), Literal::Number(2.0));
+        assert_eq!(
+            run("let double = fn(x) { x * 2 }; double(4);").unwrap(),
+            Literal::Number(8.0)
+        );
+    }
+
+    #[test]
+    fn call_errors() {
+        assert!(matches!(
+            run("fn f(a) { a } f(1, 2);").unwrap_err(),
+            WmdError::ArityMismatch {
+                expected: Arity::Exact(1),
+                got: 2,
+                ..
+            }
+        ));
+        assert!(matches!(
+            run("\"f\"();").unwrap_err(),
+            WmdError::NotCallable(1, Literal::String(_))
+        ));
+
+        // Named arguments
+        assert_eq!(
+            run("fn f(a, b, c) { a - b * c } f(1, c: 2, b: 3);").unwrap(),
+            Literal::Number(-5.0)
+        );
+        let errors = [
+            ("fn f(a) { a } f(1, b: 2);", "isn't a parameter of 'f'"),
+            ("fn f(a) { a } f(1, a: 2);", "is given more than once"),
+            (
+                "fn f(a, b) { a } f(b: 2);",
+                "is given without the arguments before it",
+            ),
+            (
+                "exercise squat {} squat(5x, bar: 20kg);",
+                "isn't accepted by exercise 'squat'",
+            ),
+        ];
+        for (src, reason) in errors {
+            match run(src).unwrap_err() {
+                WmdError::InvalidArgument(_, r) => assert_eq!(r, reason),
+                e => panic!("expected invalid argument, found {e:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn native_functions() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_native("one_rm", 1, |args| match &args[0] {
+            Literal::String(lift) if lift == "squat" => Ok(Literal::Number(180.0)),
+            lit => Err(WmdError::Native(format!("no max for {lit}"))),
+        });
+        interpreter.register_native("seed", 0, |_| Ok(Literal::Number(42.0)));
+
+        let src = "fn working(lift) { one_rm(lift) * 0.8 } [working(\"squat\"), seed()];";
+        assert_eq!(
+            run_with(&mut interpreter, src).unwrap(),
+            Literal::List(vec![Literal::Number(144.0), Literal::Number(42.0)])
+        );
+        assert!(matches!(
+            run_with(&mut interpreter, "one_rm(\"bench\");").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_))
+        ));
+        assert!(matches!(
+            run_with(&mut interpreter, "seed(1);").unwrap_err(),
+            WmdError::ArityMismatch {
+                expected: Arity::Exact(0),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn quantity_arithmetic() {
+        let q = |v, u| Literal::Quantity(Quantity::new(v, u));
+        let secs = Unit::Time(TimeUnit::Second);
+
+        assert_eq!(run("30s + 30s;").unwrap(), q(60.0, secs));
+        assert_eq!(run("2m + 30s;").unwrap(), q(150.0, secs));
+        assert_eq!(run("2m - 60s;").unwrap(), q(60.0, secs));
+        assert_eq!(run("3 * 5x;").unwrap(), q(15.0, Unit::Rep));
+        assert_eq!(run("10x / 2;").unwrap(), q(5.0, Unit::Rep));
+        assert_eq!(run("50% * 10x;").unwrap(), q(5.0, Unit::Rep));
+        assert_eq!(run("-30s;").unwrap(), q(-30.0, secs));
+        assert_eq!(run("2m / 30s;").unwrap(), Literal::Number(4.0));
+        assert_eq!(run("2m > 90s;").unwrap(), Literal::Bool(true));
+        assert_eq!(run("1m == 60s;").unwrap(), Literal::Bool(true));
+        assert_eq!(run("5x == 5s;").unwrap(), Literal::Bool(false));
+    }
+
+    #[test]
+    fn weight_arithmetic() {
+        let kg = |v| Literal::Quantity(Quantity::new(v, Unit::Weight(WeightUnit::Kilogram)));
+
+        assert_eq!(run("100kg + 2 * 2.5kg;").unwrap(), kg(105.0));
+        assert_eq!(run("80% * 150kg;").unwrap(), kg(120.0));
+        assert_eq!(run("225lb > 100kg;").unwrap(), Literal::Bool(true));
+        assert!(matches!(
+            run("100kg + 5x;").unwrap_err(),
+            WmdError::IncompatibleUnits(..)
+        ));
+    }
+
+    #[test]
+    fn distance_and_pace() {
+        let q = |v, u| Literal::Quantity(Quantity::new(v, u));
+        let pace = Unit::Pace(TimeUnit::Second, DistanceUnit::Kilometer);
+
+        assert_eq!(
+            run("5km + 400mtr;").unwrap(),
+            q(5400.0, Unit::Distance(DistanceUnit::Meter))
+        );
+        assert_eq!(
+            run("25min / 5km;").unwrap(),
+            q(5.0, Unit::Pace(TimeUnit::Minute, DistanceUnit::Kilometer))
+        );
+        assert_eq!(
+            run("270s/km * 10km;").unwrap(),
+            q(2700.0, Unit::Time(TimeUnit::Second))
+        );
+        assert_eq!(run("270s/km < 5m/km;").unwrap(), Literal::Bool(true));
+        assert_eq!(run("let km = 2; 270s/km;").unwrap(), q(270.0, pace));
+        assert!(run("5km + 5m;").is_err());
+    }
+
+    #[test]
+    fn clock_durations() {
+        assert_eq!(run("1:30 + 30s == 2m;").unwrap(), Literal::Bool(true));
+        assert_eq!(run("secs(1:02:15);").unwrap(), Literal::Number(3735.0));
+        assert_eq!(run("4:30/km * 10km > 45m;").unwrap(), Literal::Bool(false));
+    }
+
+    #[test]
+    fn prescriptions() {
+        let pct = Quantity::new(80.0, Unit::Percent);
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+
+        assert_eq!(
+            run("4x5 @ 80%;").unwrap(),
+            Literal::Prescription(Prescription::new(4, Reps::Fixed(5)).with_load(pct))
+        );
+        assert_eq!(
+            run("let base = 100kg; 3x8-12 @ base + 5kg;").unwrap(),
+            Literal::Prescription(Prescription::new(3, Reps::Range(8, 12)).with_load(kg(105.0)))
+        );
+        assert_eq!(
+            run("5x @ 60kg;").unwrap(),
+            Literal::Prescription(Prescription::new(1, Reps::Fixed(5)).with_load(kg(60.0)))
+        );
+        assert!(matches!(
+            run("5x5 @ 30s;").unwrap_err(),
+            WmdError::InvalidLoad(..)
+        ));
+        assert!(matches!(
+            run("5 @ 80%;").unwrap_err(),
+            WmdError::InvalidLoad(..)
+        ));
+        assert!(matches!(
+            run("0x @ 80%;").unwrap_err(),
+            WmdError::InvalidLoad(..)
+        ));
+        assert!(matches!(
+            run("5.5x @ 60kg;").unwrap_err(),
+            WmdError::InvalidLoad(..)
+        ));
+        for src in ["5x5 @ -10kg;", "5x5 @ 0%;", "5x5 @ 1kg / 0;"] {
+            assert!(matches!(run(src).unwrap_err(), WmdError::InvalidLoad(..)));
+        }
+        for src in ["3x5 @ 15;", "3x5 @ -2;", "3x5 @ 0.5rpe;", "3x5 @ -1rir;"] {
+            assert!(matches!(run(src).unwrap_err(), WmdError::InvalidLoad(..)));
+        }
+        assert_eq!(run("3x5 @ 10;").unwrap().to_string(), "3x5@10rpe");
+        assert_eq!(run("3x5 @ 0rir;").unwrap().to_string(), "3x5@0rir");
+        assert_eq!(
+            run("3x5 @ 80% @ 100kg;").unwrap_err().to_string(),
+            "[line 1] '3x5@80%' already has a load"
+        );
+        assert!(matches!(
+            run("3x5 @ 8 @ 9rpe;").unwrap_err(),
+            WmdError::AlreadySet(_, _, "intensity")
+        ));
+    }
+
+    #[test]
+    fn expand_prescription() {
+        let src = "let total = 0kg;
+            for set in 3x5 @ 100kg { total += 100kg * 5; }
+            total;";
+        assert_eq!(
+            run(src).unwrap(),
+            Literal::Quantity(Quantity::new(1500.0, Unit::Weight(WeightUnit::Kilogram)))
+        );
+        assert_eq!(
+            run("sets(2x3);").unwrap(),
+            Literal::List(vec![
+                Literal::Prescription(Prescription::new(1, Reps::Fixed(3))),
+                Literal::Prescription(Prescription::new(1, Reps::Fixed(3))),
+            ])
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let secs = |v| Literal::Quantity(Quantity::new(v, Unit::Time(TimeUnit::Second)));
+
+        let src = "let total = 0; for i in 1..4 { total += i; } total;";
+        assert_eq!(run(src).unwrap(), Literal::Number(6.0));
+
+        let src = "let total = 0x; for reps in 8x..=12x { total += reps; } total;";
+        assert_eq!(
+            run(src).unwrap(),
+            Literal::Quantity(Quantity::new(50.0, Unit::Rep))
+        );
+
+        assert_eq!(run("1m..90s;").unwrap().to_string(), "60s..90s");
+        assert_eq!(run("contains(60s..90s, 1m);").unwrap(), Literal::Bool(true));
+        assert_eq!(
+            run("contains(60s..90s, 90s);").unwrap(),
+            Literal::Bool(false)
+        );
+        assert_eq!(
+            run("contains(60s..=90s, 90s);").unwrap(),
+            Literal::Bool(true)
+        );
+        assert_eq!(run("clamp(2m, 60s..=90s);").unwrap(), secs(90.0));
+        assert_eq!(run("clamp(75s, 60s..=90s);").unwrap(), secs(75.0));
+        assert_eq!(run("clamp(5, 8..=12);").unwrap(), Literal::Number(8.0));
+
+        assert!(matches!(
+            run("8x..12s;").unwrap_err(),
+            WmdError::IncompatibleUnits(..)
+        ));
+        assert!(matches!(
+            run("8..12s;").unwrap_err(),
+            WmdError::BinaryNumberRequired(..)
+        ));
+        assert!(matches!(
+            run("contains(1..2, 1x);").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_))
+        ));
+        assert!(matches!(
+            run("clamp(5x, 12x..8x);").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_))
+        ));
+
+        // Ranges are iterated lazily and must be bounded
+        let src = "let n = 0; for i in 0..100000000 { n = i; if i > 2 { break; } } n;";
+        assert_eq!(run(src).unwrap(), Literal::Number(3.0));
+        let src = "let n = 0; for i in 0.5..=3 { n += 1; } n;";
+        assert_eq!(run(src).unwrap(), Literal::Number(3.0));
+        for src in ["for i in 0..1/0 {}", "1/0..5;", "0/0..=5;", "1s..1s/0;"] {
+            assert!(matches!(
+                run(src).unwrap_err(),
+                WmdError::UnboundedRange(1, _)
+            ));
+        }
+    }
+
+    #[test]
+    fn exercises() {
+        let mut interpreter = Interpreter::new();
+        let src = r#"
+            exercise squat {
+                name: "Back Squat",
+                aliases: ["bs", "squat"],
+                category: "legs",
+                equipment: ["barbell", "rack"],
+                metrics: ["load", "reps"],
+            }
+            squat(5x5 @ 80%);
+        "#;
+        assert_eq!(
+            run_with(&mut interpreter, src).unwrap().to_string(),
+            "Back Squat 5x5@80%"
+        );
+
+        let squat = interpreter.exercises().get("BS").unwrap();
+        assert_eq!(squat.category.as_deref(), Some("legs"));
+        assert_eq!(squat.equipment, vec!["barbell", "rack"]);
+        assert_eq!(squat.metrics, vec![Metric::Load, Metric::Reps]);
+
+        match run_with(&mut interpreter, "squat(8x);").unwrap() {
+            Literal::Prescription(p) => {
+                assert_eq!(p.reps, Reps::Fixed(8));
+                assert_eq!(p.exercise, Some(squat));
+            }
+            lit => panic!("expected prescription, found {lit:?}"),
+        }
+
+        assert!(matches!(
+            run_with(&mut interpreter, "squat(80%);").unwrap_err(),
+            WmdError::InvalidWork(1, _)
+        ));
+        assert!(matches!(
+            run_with(&mut interpreter, "squat(2.5x);").unwrap_err(),
+            WmdError::InvalidWork(1, _)
+        ));
+        for src in ["squat(-60s);", "squat(0mtr);"] {
+            assert!(matches!(
+                run_with(&mut interpreter, src).unwrap_err(),
+                WmdError::InvalidWork(1, _)
+            ));
+        }
+        assert!(matches!(
+            run_with(&mut interpreter, "squat(5x5, 5x5);").unwrap_err(),
+            WmdError::ArityMismatch { .. }
+        ));
+        assert!(matches!(
+            run("exercise row { reps: 5 }").unwrap_err(),
+            WmdError::ExerciseField(..)
+        ));
+        assert_eq!(
+            run(r#"exercise row { metrics: ["watts"] }"#)
+                .unwrap_err()
+                .to_string(),
+            "[line 1] Exercise field 'metrics' is invalid: \
+             Unknown metric 'watts', expected load, reps, time, distance or rpe"
+        );
+    }
+
+    fn structure(src: &str) -> Rc<Structure> {
+        let src = format!(
+            "exercise squat {{}} exercise row {{}} exercise plank {{}} exercise burpee {{}} {src}"
+        );
+        match run(&src).unwrap() {
+            Literal::Structure(s) => s,
+            lit => panic!("expected structure, found {lit:?}"),
+        }
+    }
+
+    fn labels(structure: &Structure) -> Vec<String> {
+        structure.segments().into_iter().map(|s| s.label).collect()
+    }
+
+    #[test]
+    fn structures() {
+        let superset = structure("superset { squat(3x5); row(2x8); rest(90s) }");
+        assert_eq!(superset.items.len(), 3);
+        assert_eq!(
+            labels(&superset),
+            vec![
+                "squat 1x5",
+                "row 1x8",
+                "rest",
+                "squat 1x5",
+                "row 1x8",
+                "rest",
+                "squat 1x5",
+                "rest"
+            ]
+        );
+
+        let circuit =
+            structure("circuit 2 rounds { for reps in [10x, 5x] { burpee(reps); } plank(30s) }");
+        assert_eq!(circuit.kind, StructureKind::Circuit(2));
+        assert_eq!(labels(&circuit).len(), 6);
+        assert_eq!(circuit.items[2].to_string(), "plank 30s");
+
+        let emom = structure("emom 4m { burpee(10x); squat(5x) }");
+        let segments = emom.segments();
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments[3].label, "squat 1x5");
+        assert_eq!(segments[3].duration, Some(Duration::from_secs(60)));
+
+        // Timed work is followed by rest for the rest of the minute, and a
+        // rest takes the whole minute
+        let emom = structure("emom 3m { plank(40s); rest(30s) }");
+        let segments = emom.segments();
+        assert_eq!(segments.len(), 5);
+        assert_eq!(segments[1].kind, SegmentKind::Rest);
+        assert_eq!(segments[1].duration, Some(Duration::from_secs(20)));
+        assert_eq!(segments[2].duration, Some(Duration::from_secs(60)));
+
+        let amrap = structure("amrap 12m { burpee(5x); row(250mtr) }");
+        assert_eq!(amrap.segments()[0].duration, Some(Duration::from_secs(720)));
+
+        let tabata = structure("tabata { burpee(8x) }");
+        let segments = tabata.segments();
+        assert_eq!(segments.len(), 16);
+        assert_eq!(segments[1].kind, SegmentKind::Rest);
+
+        // Each interval is a single set, so the recorded sets and rests
+        // match the segments
+        let mut workout = Workout::default();
+        workout.record(Item::Structure(structure("tabata { burpee(3x5) }")));
+        assert_eq!(workout.sets.len(), 8);
+        assert_eq!(workout.rests.len(), 8);
+        let mut workout = Workout::default();
+        workout.record(Item::Structure(structure(
+            "emom 3m { burpee(5x); rest(30s) }",
+        )));
+        assert_eq!(workout.sets.len(), 2);
+        assert_eq!(workout.rests[0].duration, Duration::from_secs(60));
+
+        // Nested structures and helper functions contribute their work once
+        let nested = structure(
+            "fn legs() { [squat(5x), squat(5x)] } circuit 2 rounds { legs(); superset { row(1x8) } }",
+        );
+        assert_eq!(nested.items.len(), 3);
+        assert_eq!(labels(&nested).len(), 6);
+
+        assert!(matches!(
+            run("circuit 1.5 rounds {}").unwrap_err(),
+            WmdError::InvalidRounds(1, _)
+        ));
+        assert!(matches!(
+            run("emom 10x {}").unwrap_err(),
+            WmdError::InvalidDuration(1, _)
+        ));
+        assert!(matches!(
+            run("emom 90s {}").unwrap_err(),
+            WmdError::PartialMinutes(1, _)
+        ));
+        assert!(matches!(
+            run("circuit 100000 rounds {}").unwrap_err(),
+            WmdError::InvalidRounds(1, _)
+        ));
+        for src in [
+            "emom 6000m {}",
+            "amrap 0s {}",
+            "amrap -5m {}",
+            "amrap 121m {}",
+        ] {
+            assert!(matches!(
+                run(src).unwrap_err(),
+                WmdError::InvalidDuration(1, _)
+            ));
+        }
+        assert!(run("circuit 100 rounds {} emom 120m {} amrap 2:00:00 {}").is_ok());
+    }
+
+    #[test]
+    fn collected_work() {
+        let sets = |src| {
+            let mut interpreter = Interpreter::new();
+            run_with(&mut interpreter, src).unwrap();
+            interpreter.take_workout().sets.len()
+        };
+
+        // Statements of the workout, blocks and loops in its place are work
+        assert_eq!(sets("3x5; { 1x5; 2x5 } for i in 1..3 { 1x1; 1x1 }"), 10);
+        assert_eq!(sets("let a = [5x5, 3x3]; a;"), 8);
+
+        // Values computed for a let or a call only contribute what they return
+        assert_eq!(sets("let x = { 3x5; 5x5 }; 1;"), 0);
+        assert_eq!(sets("let x = { 3x5; 5x5 }; x;"), 5);
+        assert_eq!(sets("fn f() { 3x5; 1 } f(); f();"), 0);
+        assert_eq!(sets("fn f() { 3x5; return 2x5; } f();"), 2);
+        assert_eq!(sets("fn f() { let s = superset { 1x5; 1x5 }; s } f();"), 2);
+    }
+
+    #[test]
+    fn programs() {
+        let mut interpreter = Interpreter::new();
+        let src = r#"
+            exercise squat {}
+            let base = 100kg;
+            program "Linear" {
+                week 1..=3 {
+                    day "Heavy" { squat(3x5 @ week * 2.5kg + base); }
+                    day "Light" { squat(2x5 @ base * 80%) }
+                }
+                week 4 { day "Deload" { squat(3x5 @ 60%); } }
+                week 2 { day "Extra" { squat(1x1 @ base + 20kg); } }
+            }
+            squat(1x1);
+        "#;
+        run_with(&mut interpreter, src).unwrap();
+
+        let programs = interpreter.take_programs();
+        assert_eq!(programs.len(), 1);
+        let program = &programs[0];
+        assert_eq!(program.name, "Linear");
+
+        let weeks: Vec<_> = program.weeks.iter().map(|w| w.number).collect();
+        assert_eq!(weeks, vec![1, 2, 3, 4]);
+        let days: Vec<_> = program.weeks[1]
+            .days
+            .iter()
+            .map(|d| d.name.as_deref().unwrap())
+            .collect();
+        assert_eq!(days, vec!["Heavy", "Light", "Extra"]);
+
+        let load = |week: usize| program.weeks[week].days[0].sets[0].load.unwrap();
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+        assert_eq!(load(0), kg(102.5));
+        assert_eq!(load(2), kg(107.5));
+        assert_eq!(program.weeks[0].days[1].sets.len(), 2);
+
+        // Days don't leak into the top level workout
+        assert_eq!(interpreter.workout().sets.len(), 1);
+
+        assert!(matches!(
+            run(r#"program "P" { week 0 { day "A" {} } }"#).unwrap_err(),
+            WmdError::InvalidWeek(1, _)
+        ));
+        assert!(matches!(
+            run(r#"program "P" { week 1s..2s { day "A" {} } }"#).unwrap_err(),
+            WmdError::InvalidWeek(1, _)
+        ));
+    }
+
+    #[test]
+    fn profile_loads() {
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_profile(
+            LifterProfile::new()
+                .with_max("Back Squat", kg(180.0))
+                .with_rounding(kg(5.0), Rounding::Nearest),
+        );
+        let src = r#"
+            exercise squat { name: "Back Squat" }
+            exercise bench {}
+            squat(5x5 @ 72%);
+            bench(5x5 @ 72%);
+        "#;
+        run_with(&mut interpreter, src).unwrap();
+
+        let loads: Vec<_> = interpreter
+            .workout()
+            .sets
+            .iter()
+            .map(|s| s.load.unwrap().to_string())
+            .collect();
+        assert_eq!(loads[0], "130kg");
+        assert_eq!(loads[5], "72%");
+    }
+
+    #[test]
+    fn plates() {
+        assert_eq!(
+            run("plates(142.5kg);").unwrap().to_string(),
+            "[25kg, 25kg, 10kg, 1.25kg]"
+        );
+        assert_eq!(
+            run("plates(135lb, 35lb);").unwrap().to_string(),
+            "[45lb, 5lb]"
+        );
+        assert_eq!(
+            run("plates(142.5kg, bar: 20kg);").unwrap().to_string(),
+            "[25kg, 25kg, 10kg, 1.25kg]"
+        );
+        assert_eq!(
+            run("plates(bar: 15kg, load: 60kg);").unwrap().to_string(),
+            "[20kg, 2.5kg]"
+        );
+        assert!(matches!(
+            run("plates(101kg);").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::UnachievableLoad(_))
+        ));
+        assert!(matches!(
+            run("plates(80%);").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_))
+        ));
+        assert!(matches!(
+            run("plates();").unwrap_err(),
+            WmdError::ArityMismatch {
+                expected: Arity::Range(1, 2),
+                got: 0,
+                ..
+            }
+        ));
+
+        let mut interpreter = Interpreter::new();
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+        interpreter.set_plate_inventory(PlateInventory::new(kg(10.0)).with_plates(kg(5.0), 2));
+        assert_eq!(
+            run_with(&mut interpreter, "plates(30kg);")
+                .unwrap()
+                .to_string(),
+            "[5kg, 5kg]"
+        );
+        assert!(run_with(&mut interpreter, "plates(40kg);").is_err());
+    }
+
+    #[test]
+    fn one_rep_max_estimates() {
+        let kg = |v| Literal::Quantity(Quantity::new(v, Unit::Weight(WeightUnit::Kilogram)));
+
+        assert_eq!(run("epley(100kg, 6x);").unwrap(), kg(120.0));
+        assert_eq!(run("brzycki(100kg, 1);").unwrap(), kg(100.0));
+        assert_eq!(run("e1rm(100kg, 6x);").unwrap(), kg(120.0));
+        assert_eq!(run("rep_max(120kg, 6x);").unwrap(), kg(100.0));
+        assert_eq!(
+            run("let tm = e1rm(100kg, 6x) * 90%; tm;").unwrap(),
+            kg(108.0)
+        );
+        assert!(matches!(
+            run("brzycki(100kg, 40x);").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_))
+        ));
+        assert!(matches!(
+            run("e1rm(5x, 100kg);").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_))
+        ));
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_formula(Formula::Brzycki);
+        assert_eq!(
+            run_with(&mut interpreter, "e1rm(90kg, 10x);").unwrap(),
+            kg(120.0)
+        );
+    }
+
+    #[test]
+    fn tempo() {
+        let secs = |v| Literal::Quantity(Quantity::new(v, Unit::Time(TimeUnit::Second)));
+
+        assert_eq!(run("T3110 * 5x;").unwrap(), secs(25.0));
+        assert_eq!(run("8x * T20X0;").unwrap(), secs(16.0));
+        assert_eq!(run("tempo(4, 0, 1, 0) * 6x;").unwrap(), secs(30.0));
+        assert_eq!(run("tempo(3, 1, 1, 0);").unwrap().to_string(), "T3110");
+        assert!(matches!(
+            run("T3110 * 30s;").unwrap_err(),
+            WmdError::BinaryNumberRequired(..)
+        ));
+        assert!(matches!(
+            run("tempo(1.5, 0, 1, 0);").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_))
+        ));
+    }
+
+    #[test]
+    fn intensity() {
+        let rpe = |v| Quantity::new(v, Unit::Intensity(IntensityUnit::Rpe));
+        let pct = |v| Literal::Quantity(Quantity::new(v, Unit::Percent));
+
+        assert_eq!(run("@8;").unwrap(), Literal::Quantity(rpe(8.0)));
+        assert_eq!(
+            run("3x5 @ 100kg @ 8;").unwrap().to_string(),
+            "3x5@100kg@8rpe"
+        );
+        assert_eq!(run("3x5 @ 2rir;").unwrap().to_string(), "3x5@2rir");
+        assert_eq!(run("8rpe == 2rir;").unwrap(), Literal::Bool(true));
+        assert_eq!(run("9rpe > 2rir;").unwrap(), Literal::Bool(true));
+        assert_eq!(run("8rpe..=1rir;").unwrap().to_string(), "8rpe..=9rpe");
+        assert_eq!(run("2rir - 1rir;").unwrap().to_string(), "1rir");
+        assert!(matches!(
+            run("8rpe + 1rir;").unwrap_err(),
+            WmdError::IncompatibleUnits(..)
+        ));
+        assert_eq!(run("percent(5x, @8);").unwrap(), pct(81.1));
+        assert_eq!(run("percent(5, 2rir);").unwrap(), pct(81.1));
+        assert_eq!(run("rpe(5x, 81.1%);").unwrap(), Literal::Quantity(rpe(8.0)));
+        assert!(matches!(
+            run("rpe(5x, 100%);").unwrap_err(),
+            WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_))
+        ));
+
+        // Hosts can supply their own chart
+        let mut interpreter = Interpreter::new();
+        interpreter.set_rpe_table(RpeTable::new(vec![100.0, 90.0, 80.0]));
+        assert_eq!(
+            run_with(&mut interpreter, "percent(1x, 9);").unwrap(),
+            pct(90.0)
+        );
+    }
+
+    #[test]
+    fn quantity_builtins() {
+        assert_eq!(run("secs(2m);").unwrap(), Literal::Number(120.0));
+        assert_eq!(run("value(5x) + 1;").unwrap(), Literal::Number(6.0));
+        assert!(
+            matches!(run("secs(5x);").unwrap_err(), WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_)))
+        );
+        assert!(
+            matches!(run("value(5);").unwrap_err(), WmdError::Call { error, .. } if matches!(*error, WmdError::Native(_)))
+        );
+    }
+
+    #[test]
+    fn incompatible_units() {
+        assert!(matches!(
+            run("5x + 30s;").unwrap_err(),
+            WmdError::IncompatibleUnits(_, Unit::Rep, Unit::Time(TimeUnit::Second))
+        ));
+        assert!(matches!(
+            run("5x < 30s;").unwrap_err(),
+            WmdError::IncompatibleUnits(..)
+        ));
+        assert!(matches!(
+            run("5x * 5x;").unwrap_err(),
+            WmdError::IncompatibleUnits(..)
+        ));
+        assert!(run("5 + 5x;").is_err());
+    }
+
+    #[test]
+    fn block_value() {
+        assert_eq!(
+            run("let warmup = { let base = 5x; base }; warmup;").unwrap(),
+            Literal::Quantity(Quantity::new(5.0, Unit::Rep))
+        );
+        assert_eq!(run("{ 1; };").unwrap(), Literal::Nil);
+        assert_eq!(run("{}").unwrap(), Literal::Nil);
+        assert_eq!(
+            run("{ let a = 1; { a + 1 } }").unwrap(),
+            Literal::Number(2.0)
+        );
+        assert_eq!(
+            run("[{ 1 }, ({ 2 })];").unwrap(),
+            Literal::List(vec![Literal::Number(1.0), Literal::Number(2.0)])
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{ast::Quantity, reporting::ErrorReporter};
+use crate::{
+    ast::{Prescription, Quantity, Reps, Tempo, TimeUnit, Unit},
+    reporting::ErrorReporter,
+};
 
 macro_rules! is_digit {
     () => {
@@ -30,6 +33,7 @@ pub enum TokenType {
     RBrace,
     LBracket,
     RBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -37,8 +41,15 @@ pub enum TokenType {
     SemiColon,
     Slash,
     Star,
+    At,
 
     // One or two character tokens
+    DotDot,
+    DotDotEqual,
+    MinusEqual,
+    PlusEqual,
+    SlashEqual,
+    StarEqual,
     Bang,
     BangEqual,
     Equal,
@@ -53,6 +64,8 @@ pub enum TokenType {
     String,
     Number,
     Quantity,
+    Prescription,
+    Tempo,
 
     // Keywords
     And,
@@ -66,6 +79,17 @@ pub enum TokenType {
     True,
     While,
     Let,
+    In,
+    Break,
+    Continue,
+    Return,
+    Exercise,
+    Superset,
+    Circuit,
+    Emom,
+    Amrap,
+    Tabata,
+    Program,
 
     Eof,
 }
@@ -75,6 +99,8 @@ pub enum TokenLiteral {
     String(String),
     Number(f64),
     Quantity(Quantity),
+    Prescription(Prescription),
+    Tempo(Tempo),
 }
 
 #[derive(Debug, Clone)]
@@ -122,12 +148,18 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
     }
 
     fn advance(&mut self) -> &str {
-        let new_current = self.current + 1;
-        let slice = &self.src[self.current..new_current];
-        self.current = new_current;
+        let slice = self.char_at(self.current).unwrap_or_default();
+        self.current += slice.len();
         slice
     }
 
+    /// The character starting at byte offset `at`. Offsets only ever
+    /// advance by whole characters so stay on char boundaries.
+    fn char_at(&self, at: usize) -> Option<&'source str> {
+        let c = self.src.get(at..)?.chars().next()?;
+        Some(&self.src[at..at + c.len_utf8()])
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.src.len()
     }
@@ -140,12 +172,42 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
             "}" => self.add_token(TokenType::RBrace),
             "[" => self.add_token(TokenType::LBracket),
             "]" => self.add_token(TokenType::RBracket),
+            ":" => self.add_token(TokenType::Colon),
             "," => self.add_token(TokenType::Comma),
-            "." => self.add_token(TokenType::Dot),
-            "-" => self.add_token(TokenType::Minus),
-            "+" => self.add_token(TokenType::Plus),
+            "." => {
+                if self.matches(".") {
+                    if self.matches("=") {
+                        self.add_token(TokenType::DotDotEqual)
+                    } else {
+                        self.add_token(TokenType::DotDot)
+                    }
+                } else {
+                    self.add_token(TokenType::Dot)
+                }
+            }
             ";" => self.add_token(TokenType::SemiColon),
-            "*" => self.add_token(TokenType::Star),
+            "@" => self.add_token(TokenType::At),
+            "-" => {
+                if self.matches("=") {
+                    self.add_token(TokenType::MinusEqual)
+                } else {
+                    self.add_token(TokenType::Minus)
+                }
+            }
+            "+" => {
+                if self.matches("=") {
+                    self.add_token(TokenType::PlusEqual)
+                } else {
+                    self.add_token(TokenType::Plus)
+                }
+            }
+            "*" => {
+                if self.matches("=") {
+                    self.add_token(TokenType::StarEqual)
+                } else {
+                    self.add_token(TokenType::Star)
+                }
+            }
             "!" => {
                 if self.matches("=") {
                     self.add_token(TokenType::BangEqual)
@@ -179,6 +241,8 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
                     while self.peek() != Some("\n") && !self.is_at_end() {
                         self.advance();
                     }
+                } else if self.matches("=") {
+                    self.add_token(TokenType::SlashEqual)
                 } else {
                     self.add_token(TokenType::Slash)
                 }
@@ -211,7 +275,26 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
             "true" => TokenType::True,
             "while" => TokenType::While,
             "let" => TokenType::Let,
-            _ => TokenType::Identifier,
+            "in" => TokenType::In,
+            "break" => TokenType::Break,
+            "continue" => TokenType::Continue,
+            "return" => TokenType::Return,
+            "exercise" => TokenType::Exercise,
+            "superset" => TokenType::Superset,
+            "circuit" => TokenType::Circuit,
+            "emom" => TokenType::Emom,
+            "amrap" => TokenType::Amrap,
+            "tabata" => TokenType::Tabata,
+            "program" => TokenType::Program,
+            _ => {
+                if let Some(tempo) = Tempo::parse(text) {
+                    return self.add_token_with_literal(
+                        TokenType::Tempo,
+                        Some(TokenLiteral::Tempo(tempo)),
+                    );
+                }
+                TokenType::Identifier
+            }
         };
 
         self.add_token(typ)
@@ -222,6 +305,14 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
             self.advance();
         }
 
+        if self.peek() == Some(":") && matches!(self.peek_next(), Some(is_digit!())) {
+            return self.clock();
+        }
+
+        if self.peek() == Some("x") && matches!(self.peek_next(), Some(is_digit!())) {
+            return self.prescription();
+        }
+
         // Look for fractional part
         if self.peek() == Some(".") && matches!(self.peek_next(), Some(is_digit!())) {
             // Consume the "."
@@ -232,26 +323,154 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
             }
         }
 
-        // Look for unit
-        if let Some(u @ ("s" | "m" | "%" | "x")) = self.peek() {
-            let unit = u.parse().unwrap();
-            // Consume the unit
+        let number_end = self.current;
+        let number: f64 = self.src[self.start..number_end].parse().unwrap();
+
+        // Look for unit, either '%' or a run of letters such as 'kg'
+        if self.peek() == Some("%") {
             self.advance();
+        } else {
+            while let Some(is_alpha!()) = self.peek() {
+                self.advance();
+            }
+        }
+
+        let suffix = &self.src[number_end..self.current];
+        if suffix.is_empty() {
+            self.add_token_with_literal(TokenType::Number, Some(TokenLiteral::Number(number)))
+        } else if let Ok(unit) = suffix.parse() {
+            let unit = match unit {
+                Unit::Time(t) => self.pace_suffix(t),
+                unit => unit,
+            };
 
-            let number = &self.src[self.start..self.current - 1];
             self.add_token_with_literal(
                 TokenType::Quantity,
-                Some(TokenLiteral::Quantity(Quantity::new(
-                    number.parse().unwrap(),
-                    unit,
-                ))),
+                Some(TokenLiteral::Quantity(Quantity::new(number, unit))),
             )
         } else {
-            let number = &self.src[self.start..self.current];
-            self.add_token_with_literal(
-                TokenType::Number,
-                Some(TokenLiteral::Number(number.parse().unwrap())),
-            )
+            self.reporter
+                .error(self.line, &format!("Unknown unit '{suffix}'."))
+        }
+    }
+
+    /// Sets and reps such as `5x5` or with a rep range `3x8-12`.
+    /// Assumes the sets have been consumed.
+    fn prescription(&mut self) {
+        let sets = self.integer(self.start);
+
+        // Consume the "x"
+        self.advance();
+        let low = self.integer(self.current);
+
+        let mut reps = low.map(Reps::Fixed);
+        if self.peek() == Some("-") && matches!(self.peek_next(), Some(is_digit!())) {
+            // Consume the "-"
+            self.advance();
+            let high = self.integer(self.current);
+
+            reps = match (low, high) {
+                (Some(low), Some(high)) if low <= high => Some(Reps::Range(low, high)),
+                _ => None,
+            };
+        }
+
+        // A unit straight after the reps, `5x5kg`, is a load missing its '@'
+        if matches!(self.peek(), Some(is_alpha!() | "%")) {
+            while let Some(is_alpha!() | "%") = self.peek() {
+                self.advance();
+            }
+            return self
+                .reporter
+                .error(self.line, "A prescription takes its load after '@'.");
+        }
+
+        match (sets, reps) {
+            (Some(0), _) | (_, Some(Reps::Fixed(0) | Reps::Range(0, _))) => self
+                .reporter
+                .error(self.line, "Sets and reps must be at least 1."),
+            (Some(sets), _) if sets > Prescription::MAX_SETS => self.reporter.error(
+                self.line,
+                &format!("Expect at most {} sets.", Prescription::MAX_SETS),
+            ),
+            (Some(sets), Some(reps)) => self.add_token_with_literal(
+                TokenType::Prescription,
+                Some(TokenLiteral::Prescription(Prescription::new(sets, reps))),
+            ),
+            _ => self.reporter.error(self.line, "Invalid sets and reps."),
+        }
+    }
+
+    /// Consumes digits following start, `None` if they don't fit in a u32
+    fn integer(&mut self, start: usize) -> Option<u32> {
+        while let Some(is_digit!()) = self.peek() {
+            self.advance();
+        }
+
+        self.src[start..self.current].parse().ok()
+    }
+
+    /// Clock style durations `mm:ss` or `h:mm:ss`, lexed as seconds.
+    /// Assumes the leading digits have been consumed.
+    fn clock(&mut self) {
+        let src = self.src;
+        let mut parts = vec![&src[self.start..self.current]];
+
+        while self.peek() == Some(":") && matches!(self.peek_next(), Some(is_digit!())) {
+            // Consume the ":"
+            self.advance();
+
+            let part_start = self.current;
+            while let Some(is_digit!()) = self.peek() {
+                self.advance();
+            }
+            parts.push(&src[part_start..self.current]);
+        }
+
+        if parts.len() > 3 || parts[1..].iter().any(|p| p.len() != 2) {
+            return self
+                .reporter
+                .error(self.line, "Expect clock time as mm:ss or h:mm:ss.");
+        }
+
+        let parts: Vec<f64> = parts.iter().map(|p| p.parse().unwrap()).collect();
+        if parts[1..].iter().any(|p| *p >= 60.0) {
+            return self.reporter.error(
+                self.line,
+                "Minutes and seconds in a clock time must be less than 60.",
+            );
+        }
+
+        let seconds = parts.iter().fold(0.0, |total, part| total * 60.0 + part);
+        let unit = self.pace_suffix(TimeUnit::Second);
+        self.add_token_with_literal(
+            TokenType::Quantity,
+            Some(TokenLiteral::Quantity(Quantity::new(seconds, unit))),
+        )
+    }
+
+    /// A time unit directly followed by '/' and a distance unit is a pace
+    /// such as `4m/km`. Only consumes the '/' if a valid pace is formed.
+    fn pace_suffix(&mut self, time: TimeUnit) -> Unit {
+        if self.peek() != Some("/") || !matches!(self.peek_next(), Some(is_alpha!())) {
+            return Unit::Time(time);
+        }
+
+        let checkpoint = self.current;
+        // Consume the "/"
+        self.advance();
+
+        let distance_start = self.current;
+        while let Some(is_alpha!()) = self.peek() {
+            self.advance();
+        }
+
+        match self.src[distance_start..self.current].parse() {
+            Ok(distance) => Unit::Pace(time, distance),
+            Err(_) => {
+                self.current = checkpoint;
+                Unit::Time(time)
+            }
         }
     }
 
@@ -265,7 +484,7 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
         }
 
         if self.is_at_end() {
-            unimplemented!("unterminated string")
+            return self.reporter.error(self.line, "Unterminated string.");
         }
 
         // The closing "
@@ -277,35 +496,21 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
     }
 
     fn matches(&mut self, expected: &str) -> bool {
-        if self.is_at_end() {
+        if self.peek() != Some(expected) {
             return false;
         }
 
-        let slice = &self.src[self.current..self.current + 1];
-        if slice != expected {
-            return false;
-        }
-
-        self.current += 1;
+        self.current += expected.len();
         true
     }
 
     fn peek(&self) -> Option<&str> {
-        if self.is_at_end() {
-            None
-        } else {
-            let slice = &self.src[self.current..self.current + 1];
-            Some(slice)
-        }
+        self.char_at(self.current)
     }
 
     fn peek_next(&self) -> Option<&str> {
-        if self.current + 1 >= self.src.len() {
-            None
-        } else {
-            let slice = &self.src[self.current + 1..self.current + 2];
-            Some(slice)
-        }
+        let current = self.peek()?;
+        self.char_at(self.current + current.len())
     }
 
     fn add_token(&mut self, typ: TokenType) {
@@ -325,7 +530,7 @@ impl<'source, R: ErrorReporter> Lexer<'source, R> {
 
 #[cfg(test)]
 mod tests {
-    use crate::reporting::StdoutReporter;
+    use crate::reporting::{Diagnostics, StdoutReporter};
 
     use super::*;
 
@@ -333,19 +538,187 @@ mod tests {
     fn lexer() {
         let wmd_content = r#"// this is a comment
             (( )){}[] // grouping stuff
-            !*+-/=<> <= == // operators
+            !*+-/ =<> <= == // operators
+            += -= *= /=
             "this is a string"
             123 + 5.55
             ident_test
             and else false
             for fn if nil or
             true while
+            in break continue return
             30s 2m 30x 50%
             "#;
         let reporter = StdoutReporter;
-        let lexer = Lexer::new(&wmd_content, &reporter);
+        let lexer = Lexer::new(wmd_content, &reporter);
         let tokens = lexer.scan_tokens();
-        println!("{tokens:#?}");
-        panic!()
+        let types: Vec<_> = tokens.iter().map(|t| t.typ).collect();
+
+        use TokenType::*;
+        assert_eq!(
+            types,
+            vec![
+                LParen, LParen, RParen, RParen, LBrace, RBrace, LBracket, RBracket, Bang, Star,
+                Plus, Minus, Slash, Equal, Less, Greater, LessEqual, EqualEqual, PlusEqual,
+                MinusEqual, StarEqual, SlashEqual, String, Number, Plus, Number, Identifier, And,
+                Else, False, For, Fn, If, Nil, Or, True, While, In, Break, Continue, Return,
+                Quantity, Quantity, Quantity, Quantity, Eof
+            ]
+        );
+        assert_eq!(tokens.last().unwrap().line, 13);
+    }
+
+    #[test]
+    fn unit_suffixes() {
+        let reporter = StdoutReporter;
+        let tokens =
+            Lexer::new("100kg 225lb 5x 2zz 400mtr 4m/km 8rpe 2rir", &reporter).scan_tokens();
+
+        let units: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match &t.literal {
+                Some(TokenLiteral::Quantity(q)) => Some(q.unit().to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(units, vec!["kg", "lb", "x", "mtr", "m/km", "rpe", "rir"]);
+
+        // Unknown units are reported and skipped
+        assert_eq!(tokens.len(), 8);
+
+        // Division by a variable isn't mistaken for a pace
+        let tokens = Lexer::new("30s/n", &reporter).scan_tokens();
+        assert_eq!(tokens[1].typ, TokenType::Slash);
+    }
+
+    #[test]
+    fn clock_literals() {
+        let reporter = StdoutReporter;
+        let tokens = Lexer::new("1:30 1:02:15 4:30/km 1:75 1:5", &reporter).scan_tokens();
+
+        let quantities: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match &t.literal {
+                Some(TokenLiteral::Quantity(q)) => Some(q.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(quantities, vec!["90s", "3735s", "270s/km"]);
+
+        // Invalid clock times are reported and skipped
+        assert_eq!(tokens.len(), 4);
+    }
+
+    #[test]
+    fn prescriptions() {
+        let reporter = StdoutReporter;
+        let tokens = Lexer::new("5x5 3x8-12 4x5@80% 3x12-8 5x", &reporter).scan_tokens();
+
+        let types: Vec<_> = tokens.iter().map(|t| t.typ).collect();
+        use TokenType::*;
+        assert_eq!(
+            types,
+            vec![
+                Prescription,
+                Prescription,
+                Prescription,
+                At,
+                Quantity,
+                Quantity,
+                Eof
+            ]
+        );
+
+        match &tokens[1].literal {
+            Some(TokenLiteral::Prescription(p)) => assert_eq!(p.reps, Reps::Range(8, 12)),
+            lit => panic!("expected prescription, found {lit:?}"),
+        }
+    }
+
+    #[test]
+    fn invalid_prescriptions() {
+        let diagnostics = Diagnostics::new();
+        let tokens = Lexer::new("0x5 5x0 101x1 5x5kg 100x1", &diagnostics).scan_tokens();
+        assert_eq!(tokens.len(), 2);
+
+        let messages: Vec<_> = diagnostics
+            .into_inner()
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Error: Sets and reps must be at least 1.",
+                "Error: Sets and reps must be at least 1.",
+                "Error: Expect at most 100 sets.",
+                "Error: A prescription takes its load after '@'.",
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_and_unterminated_strings() {
+        let diagnostics = Diagnostics::new();
+        let tokens = Lexer::new("note(\"8–12 reps\"); 5 – 3; \"open", &diagnostics).scan_tokens();
+
+        match &tokens[2].literal {
+            Some(TokenLiteral::String(s)) => assert_eq!(s, "8–12 reps"),
+            lit => panic!("expected string, found {lit:?}"),
+        }
+        let messages: Vec<_> = diagnostics
+            .into_inner()
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Error: Unexpected character.",
+                "Error: Unterminated string."
+            ]
+        );
+    }
+
+    #[test]
+    fn tempos() {
+        let reporter = StdoutReporter;
+        let tokens = Lexer::new("T3110 T20X0 T311 Tempo", &reporter).scan_tokens();
+
+        let types: Vec<_> = tokens.iter().map(|t| t.typ).collect();
+        use TokenType::*;
+        assert_eq!(types, vec![Tempo, Tempo, Identifier, Identifier, Eof]);
+
+        match &tokens[1].literal {
+            Some(TokenLiteral::Tempo(t)) => assert_eq!(t.rep_seconds(), 2),
+            lit => panic!("expected tempo, found {lit:?}"),
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let reporter = StdoutReporter;
+        let tokens = Lexer::new("1..5 8x..=12x 1.5..2 a.b", &reporter).scan_tokens();
+
+        let types: Vec<_> = tokens.iter().map(|t| t.typ).collect();
+        use TokenType::*;
+        assert_eq!(
+            types,
+            vec![
+                Number,
+                DotDot,
+                Number,
+                Quantity,
+                DotDotEqual,
+                Quantity,
+                Number,
+                DotDot,
+                Number,
+                Identifier,
+                Dot,
+                Identifier,
+                Eof
+            ]
+        );
     }
 }
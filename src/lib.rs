@@ -1,16 +1,35 @@
-use ast::{BinaryOp, OpToken, UnaryOp};
-use lexer::TokenType;
+use ast::{BinaryOp, IdentToken, Literal, OpToken, Prescription, Quantity, Range, UnaryOp, Unit};
+use estimate::Formula;
+use function::Arity;
+use intensity::RpeTable;
+use interpreter::Interpreter;
+use lexer::{Lexer, TokenType};
+use parser::Parser;
+use plates::PlateInventory;
+use profile::LifterProfile;
+use reporting::Diagnostics;
 use thiserror::Error;
+use workout::{Program, StructureKind, Workout};
 
+pub mod analytics;
 pub mod ast;
+mod builtins;
+pub mod environment;
+pub mod estimate;
+pub mod exercise;
+pub mod function;
+pub mod intensity;
 pub mod interpreter;
 pub mod lexer;
 pub mod parser;
+pub mod plates;
+pub mod profile;
 pub mod reporting;
+pub mod workout;
 
 #[derive(Debug, Error)]
 pub enum WmdError {
-    #[error("invalid unit, expected x, %, s or m")]
+    #[error("invalid unit, expected x, %, s, m, min, kg, lb, mtr, km, mi, rpe, rir or a pace such as m/km")]
     BadUnit,
     #[error("error parsing")]
     ParseError,
@@ -24,4 +43,355 @@ pub enum WmdError {
     BinaryNumberRequired(OpToken<BinaryOp>),
     #[error("[line {}] Binary operator '+' requires a number or string", .0.line)]
     NumberOrStringRequired(OpToken<BinaryOp>),
+    #[error("[line {}] Binary operator '{}' can't be applied to units '{}' and '{}'", .0.line, .0.typ, .1, .2)]
+    IncompatibleUnits(OpToken<BinaryOp>, Unit, Unit),
+    /// Operands are boxed to keep results small
+    #[error("[line {}] Expected sets and reps @ a load, found '{}' @ '{}'", .0.line, .1, .2)]
+    InvalidLoad(OpToken<BinaryOp>, Box<Literal>, Box<Literal>),
+    #[error("[line {}] '{}' already has a {}", .0.line, .1, .2)]
+    AlreadySet(OpToken<BinaryOp>, Box<Prescription>, &'static str),
+    #[error("[line {}] Undefined variable '{}'", .0.line, .0.ident)]
+    UndefinedVariable(IdentToken),
+    #[error("[line {0}] Can't iterate over '{1}'")]
+    NotIterable(usize, Literal),
+    #[error("[line {0}] Expected a range with finite ends, found '{1}'")]
+    UnboundedRange(usize, Range),
+    #[error("[line {}] Argument '{}' {}", .0.line, .0.ident, .1)]
+    InvalidArgument(IdentToken, String),
+    #[error("[line {0}] Can only call functions, found '{1}'")]
+    NotCallable(usize, Literal),
+    #[error("[line {}] Exercise field '{}' {}", .0.line, .0.ident, .1)]
+    ExerciseField(IdentToken, String),
+    #[error("[line {0}] Expected sets and reps, a time or a distance for exercise, found '{1}'")]
+    InvalidWork(usize, Literal),
+    #[error(
+        "[line {0}] Expected a whole number of rounds from 1 to {}, found '{1}'",
+        StructureKind::MAX_ROUNDS
+    )]
+    InvalidRounds(usize, Literal),
+    #[error(
+        "[line {0}] Expected a positive duration of at most {}m, found '{1}'",
+        StructureKind::MAX_MINUTES
+    )]
+    InvalidDuration(usize, Literal),
+    #[error("[line {0}] Expected an emom to last a whole number of minutes, found '{1}'")]
+    PartialMinutes(usize, Quantity),
+    #[error("[line {0}] Expected a week number or range of weeks, found '{1}'")]
+    InvalidWeek(usize, Literal),
+    #[error("Expected the script to declare a program")]
+    MissingProgram,
+    #[error("Can't load '{0}' with the available plates")]
+    UnachievableLoad(Quantity),
+    #[error("Unknown metric '{0}', expected load, reps, time, distance or rpe")]
+    UnknownMetric(String),
+    #[error("[line {line}] Expected {expected} arguments but got {got}")]
+    ArityMismatch {
+        line: usize,
+        expected: Arity,
+        got: usize,
+    },
+    /// Raised by native functions registered by the host
+    #[error("{0}")]
+    Native(String),
+    /// Error without a line of its own, such as one raised by a native
+    /// function, located at the call it came from
+    #[error("[line {line}] {error}")]
+    Call { line: usize, error: Box<WmdError> },
+    /// Signal unwinding to the nearest enclosing loop, only surfaces
+    /// if the parser failed to reject a misplaced 'break'
+    #[error("[line {0}] Can't use 'break' outside of a loop")]
+    Break(usize),
+    /// Signal unwinding to the nearest enclosing loop, only surfaces
+    /// if the parser failed to reject a misplaced 'continue'
+    #[error("[line {0}] Can't use 'continue' outside of a loop")]
+    Continue(usize),
+    /// Signal unwinding to the nearest enclosing function call, only
+    /// surfaces if the parser failed to reject a misplaced 'return'
+    #[error("[line {0}] Can't return from top-level code")]
+    Return(usize, Literal),
+}
+
+impl WmdError {
+    /// Line of the source the error was raised at, if known
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            WmdError::UnaryNumberRequired(op) => Some(op.line),
+            WmdError::BinaryNumberRequired(op)
+            | WmdError::NumberOrStringRequired(op)
+            | WmdError::IncompatibleUnits(op, ..)
+            | WmdError::InvalidLoad(op, ..)
+            | WmdError::AlreadySet(op, ..) => Some(op.line),
+            WmdError::UndefinedVariable(ident)
+            | WmdError::ExerciseField(ident, _)
+            | WmdError::InvalidArgument(ident, _) => Some(ident.line),
+            WmdError::NotIterable(line, _)
+            | WmdError::UnboundedRange(line, _)
+            | WmdError::NotCallable(line, _)
+            | WmdError::InvalidWork(line, _)
+            | WmdError::InvalidRounds(line, _)
+            | WmdError::InvalidDuration(line, _)
+            | WmdError::PartialMinutes(line, _)
+            | WmdError::InvalidWeek(line, _)
+            | WmdError::ArityMismatch { line, .. }
+            | WmdError::Call { line, .. }
+            | WmdError::Break(line)
+            | WmdError::Continue(line)
+            | WmdError::Return(line, _) => Some(*line),
+            WmdError::BadUnit
+            | WmdError::ParseError
+            | WmdError::UnexpectedTokenOp(_)
+            | WmdError::ExpectedIdentifier
+            | WmdError::MissingProgram
+            | WmdError::UnachievableLoad(_)
+            | WmdError::UnknownMetric(_)
+            | WmdError::Native(_) => None,
+        }
+    }
+}
+
+/// Host supplied settings a script is run with, the defaults of
+/// [`Interpreter`] unless given
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub profile: LifterProfile,
+    pub plates: Option<PlateInventory>,
+    pub rpe_table: RpeTable,
+    pub formula: Formula,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_profile(mut self, profile: LifterProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_plates(mut self, plates: PlateInventory) -> Self {
+        self.plates = Some(plates);
+        self
+    }
+
+    pub fn with_rpe_table(mut self, rpe_table: RpeTable) -> Self {
+        self.rpe_table = rpe_table;
+        self
+    }
+
+    pub fn with_formula(mut self, formula: Formula) -> Self {
+        self.formula = formula;
+        self
+    }
+
+    fn apply(&self, interpreter: &mut Interpreter) {
+        interpreter.set_profile(self.profile.clone());
+        if let Some(plates) = &self.plates {
+            interpreter.set_plate_inventory(plates.clone());
+        }
+        interpreter.set_rpe_table(self.rpe_table.clone());
+        interpreter.set_formula(self.formula);
+    }
+}
+
+/// Runs a complete script, returning the workout it describes or the
+/// errors that stopped it
+pub fn run(src: &str, options: &Options) -> Result<Workout, Diagnostics> {
+    run_with(src, options, Interpreter::take_workout)
+}
+
+/// Runs a script declaring a program, returning the first program it
+/// declares expanded week by week
+pub fn run_program(src: &str, options: &Options) -> Result<Program, Diagnostics> {
+    run_with(src, options, |interpreter| {
+        interpreter.take_programs().into_iter().next()
+    })
+    .and_then(|program| {
+        program.ok_or_else(|| {
+            let diagnostics = Diagnostics::new();
+            diagnostics.push(&WmdError::MissingProgram);
+            diagnostics
+        })
+    })
+}
+
+fn run_with<T, F>(src: &str, options: &Options, output: F) -> Result<T, Diagnostics>
+where
+    F: FnOnce(&mut Interpreter) -> T,
+{
+    let diagnostics = Diagnostics::new();
+    let tokens = Lexer::new(src, &diagnostics).scan_tokens();
+    let stmts = Parser::new(tokens, &diagnostics).parse();
+
+    let stmts = match stmts {
+        Ok(stmts) if diagnostics.is_empty() => stmts,
+        Ok(_) => return Err(diagnostics),
+        Err(e) => {
+            diagnostics.push(&e);
+            return Err(diagnostics);
+        }
+    };
+
+    let mut interpreter = Interpreter::new();
+    options.apply(&mut interpreter);
+    match interpreter.interpret(&stmts) {
+        Ok(_) => Ok(output(&mut interpreter)),
+        Err(e) => {
+            diagnostics.push(&e);
+            Err(diagnostics)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        ast::{Reps, WeightUnit},
+        workout::{Item, SegmentKind, Target},
+    };
+
+    use super::*;
+
+    #[test]
+    fn run_workout() {
+        let src = r#"
+            exercise squat { name: "Back Squat" }
+            exercise row {}
+            workout("Legs");
+            note("Belt on top sets");
+
+            section("Main");
+            squat(3x5 @ 100kg);
+            rest(2m);
+            section("Finisher");
+            circuit 2 rounds { row(250mtr); squat(10x) }
+            let warmup = squat(5x);
+        "#;
+        let workout = run(src, &Options::default()).unwrap();
+
+        assert_eq!(workout.name.as_deref(), Some("Legs"));
+        assert_eq!(workout.notes, vec!["Belt on top sets"]);
+
+        let sections: Vec<_> = workout.sections.iter().map(|s| s.name.as_deref()).collect();
+        assert_eq!(sections, vec![Some("Main"), Some("Finisher")]);
+        assert!(matches!(
+            workout.sections[1].items[..],
+            [Item::Structure(_)]
+        ));
+
+        assert_eq!(workout.sets.len(), 7);
+        assert_eq!(workout.sets[0].target, Target::Reps(Reps::Fixed(5)));
+        assert_eq!(workout.sets[3].section, 1);
+        assert!(matches!(workout.sets[3].target, Target::Amount(_)));
+        assert_eq!(
+            workout.sets[6].exercise.as_ref().unwrap().name,
+            "Back Squat"
+        );
+
+        assert_eq!(workout.rests.len(), 1);
+        assert_eq!(workout.rests[0].after, 3);
+        assert_eq!(workout.rests[0].duration, Duration::from_secs(120));
+    }
+
+    #[test]
+    fn run_options() {
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+        let options = Options::new()
+            .with_profile(LifterProfile::new().with_max("squat", kg(150.0)))
+            .with_formula(Formula::Brzycki);
+
+        let workout = run("exercise squat {} squat(5x5 @ 80%);", &options).unwrap();
+        assert_eq!(workout.sets[0].load, Some(kg(120.0)));
+
+        assert!(run("plates(60kg);", &options).is_ok());
+        let options = options.with_plates(PlateInventory::new(kg(20.0)));
+        assert!(run("plates(60kg);", &options).is_err());
+    }
+
+    #[test]
+    fn run_timeline() {
+        let src = r#"
+            exercise plank {}
+            exercise squat {}
+            squat(2x5);
+            rest(90s);
+            section("Conditioning");
+            emom 2m { plank(30s) }
+            tabata { squat(20s) }
+            rest(1m);
+        "#;
+        let timeline = run(src, &Options::default()).unwrap().timeline();
+        assert_eq!(timeline.len(), 2 + 1 + 1 + 4 + 16 + 1);
+
+        assert_eq!(timeline[2].kind, SegmentKind::Rest);
+        assert_eq!(timeline[2].cue.as_deref(), Some("next: plank 30s"));
+        assert_eq!(timeline[3].kind, SegmentKind::Transition);
+        assert_eq!(timeline[3].label, "Conditioning");
+        assert_eq!(timeline[3].start, Duration::from_secs(90));
+        assert_eq!(timeline[5].kind, SegmentKind::Rest);
+        assert_eq!(timeline[5].start, Duration::from_secs(120));
+        assert_eq!(timeline[6].start, Duration::from_secs(150));
+        assert_eq!(timeline[8].label, "squat 20s");
+        assert_eq!(timeline[9].cue.as_deref(), Some("next: squat 20s"));
+
+        let last = timeline.last().unwrap();
+        assert_eq!(last.start, Duration::from_secs(450));
+        assert_eq!(last.cue, None);
+        assert_eq!(last.to_string(), "7:30 rest 1:00");
+        assert_eq!(timeline[0].to_string(), "0:00 work squat 1x5");
+    }
+
+    #[test]
+    fn run_programs() {
+        let src = r#"program "Block" { week 1..3 { day "A" { 5x5 @ 80% + week * 2.5%; } } }"#;
+        let program = run_program(src, &Options::default()).unwrap();
+        assert_eq!(program.weeks.len(), 2);
+        assert_eq!(program.weeks[1].days[0].sets.len(), 5);
+
+        let diagnostics = run_program("5x5;", &Options::default())
+            .unwrap_err()
+            .into_inner();
+        assert_eq!(
+            diagnostics[0].message,
+            "Expected the script to declare a program"
+        );
+    }
+
+    #[test]
+    fn run_diagnostics() {
+        let diagnostics = run("let a = ;\n5x5 @ 30s;", &Options::default())
+            .unwrap_err()
+            .into_inner();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(1));
+
+        let diagnostics = run("5x5 @ 80%;\n5x5 @ 30s;", &Options::default())
+            .unwrap_err()
+            .into_inner();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert_eq!(
+            diagnostics[0].message,
+            "Expected sets and reps @ a load, found '5x5' @ '30s'"
+        );
+        assert!(diagnostics[0].to_string().starts_with("[line 2]"));
+
+        // Errors raised by builtins are located at their call
+        let diagnostics = run("let a = 1;\nplates(101kg);", &Options::default())
+            .unwrap_err()
+            .into_inner();
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert_eq!(
+            diagnostics[0].message,
+            "Can't load '101kg' with the available plates"
+        );
+
+        assert!(run(r#"note("8–12 reps");"#, &Options::default()).is_ok());
+        let diagnostics = run(r#"note("8–12 reps);"#, &Options::default())
+            .unwrap_err()
+            .into_inner();
+        assert_eq!(diagnostics[0].message, "Error: Unterminated string.");
+    }
 }
@@ -1,6 +1,11 @@
+use std::rc::Rc;
+
 use crate::{
-    ast::{Expr, Literal, Stmt},
-    lexer::{Token, TokenType},
+    ast::{
+        BinaryOp, DayDecl, ExerciseDecl, Expr, FnDecl, IdentToken, Literal, OpToken, ProgramDecl,
+        Stmt, StructureType, WeekDecl,
+    },
+    lexer::{Token, TokenLiteral, TokenType},
     reporting::ErrorReporter,
     WmdError,
 };
@@ -24,6 +29,8 @@ macro_rules! match_tok {
 pub struct Parser<'source, R> {
     tokens: Vec<Token<'source>>,
     current: usize,
+    loop_depth: usize,
+    fn_depth: usize,
     reporter: R,
 }
 
@@ -32,6 +39,8 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
         Self {
             tokens,
             current: 0,
+            loop_depth: 0,
+            fn_depth: 0,
             reporter,
         }
     }
@@ -48,6 +57,13 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
     fn declaration(&mut self) -> Result<Stmt, WmdError> {
         let decl = if match_tok!(self, TokenType::Let) {
             self.let_declaration()
+        } else if self.check(TokenType::Fn) && self.check_next(TokenType::Identifier) {
+            self.advance();
+            self.fn_declaration()
+        } else if match_tok!(self, TokenType::Exercise) {
+            self.exercise_declaration()
+        } else if match_tok!(self, TokenType::Program) {
+            self.program_declaration()
         } else {
             self.statement()
         };
@@ -75,21 +91,328 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
         Ok(Stmt::Let(name, inititializer))
     }
 
+    fn fn_declaration(&mut self) -> Result<Stmt, WmdError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect function name.")?
+            .try_into()?;
+
+        Ok(Stmt::Fn(Rc::new(self.function(Some(name))?)))
+    }
+
+    fn exercise_declaration(&mut self) -> Result<Stmt, WmdError> {
+        let ident = self
+            .consume(TokenType::Identifier, "Expect exercise name.")?
+            .try_into()?;
+        self.consume(TokenType::LBrace, "Expect '{' before exercise fields.")?;
+
+        let mut fields = Vec::new();
+
+        // Allows trailing comma after the last field
+        while !self.check(TokenType::RBrace) && !self.is_at_end() {
+            let field = self
+                .consume(TokenType::Identifier, "Expect field name.")?
+                .try_into()?;
+            self.consume(TokenType::Colon, "Expect ':' after field name.")?;
+            fields.push((field, self.expression()?));
+
+            if !self.check(TokenType::RBrace) {
+                self.consume(TokenType::Comma, "Expect ',' between exercise fields.")?;
+            }
+        }
+
+        self.consume(TokenType::RBrace, "Expect '}' after exercise fields.")?;
+        Ok(Stmt::Exercise(ExerciseDecl { ident, fields }))
+    }
+
+    /// Within a program `week` and `day` introduce weeks and days, `week`
+    /// is otherwise an ordinary identifier so it can be bound to the
+    /// current week number
+    fn program_declaration(&mut self) -> Result<Stmt, WmdError> {
+        let name = self.string("Expect program name.")?;
+        self.consume(TokenType::LBrace, "Expect '{' before program body.")?;
+
+        let mut weeks = Vec::new();
+        while !self.check(TokenType::RBrace) && !self.is_at_end() {
+            let line = self.consume_word("week", "Expect 'week' in program.")?.line;
+            let numbers = self.range()?;
+            self.consume(TokenType::LBrace, "Expect '{' after week.")?;
+
+            let mut days = Vec::new();
+            while !self.check(TokenType::RBrace) && !self.is_at_end() {
+                self.consume_word("day", "Expect 'day' in week.")?;
+                let name = self.string("Expect day name.")?;
+                self.consume(TokenType::LBrace, "Expect '{' before day body.")?;
+                days.push(DayDecl {
+                    name,
+                    body: self.block()?,
+                });
+            }
+
+            self.consume(TokenType::RBrace, "Expect '}' after week.")?;
+            weeks.push(WeekDecl {
+                line,
+                weeks: numbers,
+                days,
+            });
+        }
+
+        self.consume(TokenType::RBrace, "Expect '}' after program body.")?;
+        Ok(Stmt::Program(ProgramDecl { name, weeks }))
+    }
+
+    /// Parses parameters and body of a function, assumes name (if any)
+    /// has already been consumed
+    fn function(&mut self, name: Option<IdentToken>) -> Result<FnDecl, WmdError> {
+        self.consume(TokenType::LParen, "Expect '(' before parameters.")?;
+
+        let mut params: Vec<IdentToken> = Vec::new();
+        if !self.check(TokenType::RParen) {
+            loop {
+                let token = self
+                    .consume(TokenType::Identifier, "Expect parameter name.")?
+                    .clone();
+                let param: IdentToken = (&token).try_into()?;
+                if params.iter().any(|p| p.ident == param.ident) {
+                    return Err(self.error(&token, "Duplicate parameter name."));
+                }
+                params.push(param);
+
+                if !match_tok!(self, TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(TokenType::RParen, "Expect ')' after parameters.")?;
+        self.consume(TokenType::LBrace, "Expect '{' before function body.")?;
+
+        // Loops don't extend into function bodies
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.fn_depth += 1;
+        let body = self.block();
+        self.fn_depth -= 1;
+        self.loop_depth = loop_depth;
+
+        Ok(FnDecl {
+            name,
+            params,
+            body: body?,
+        })
+    }
+
     fn statement(&mut self) -> Result<Stmt, WmdError> {
-        self.expression_statement()
+        if match_tok!(self, TokenType::While) {
+            self.while_statement()
+        } else if match_tok!(self, TokenType::For) {
+            self.for_statement()
+        } else if match_tok!(self, TokenType::Break, TokenType::Continue) {
+            self.loop_control_statement()
+        } else if match_tok!(self, TokenType::Return) {
+            self.return_statement()
+        } else {
+            self.expression_statement()
+        }
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, WmdError> {
+        let condition = self.or()?;
+        let body = self.loop_body("Expect '{' after while condition.")?;
+
+        Ok(Stmt::While(condition, body))
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt, WmdError> {
+        let ident = self
+            .consume(TokenType::Identifier, "Expect loop variable name.")?
+            .try_into()?;
+        self.consume(TokenType::In, "Expect 'in' after loop variable.")?;
+
+        let iterable = self.or()?;
+        let body = self.loop_body("Expect '{' after for iterable.")?;
+
+        Ok(Stmt::For(ident, iterable, body))
+    }
+
+    /// Parses a block while tracking loop nesting so 'break' and 'continue'
+    /// can be rejected outside of loops
+    fn loop_body(&mut self, msg: &str) -> Result<Expr, WmdError> {
+        self.consume(TokenType::LBrace, msg)?;
+
+        self.loop_depth += 1;
+        let body = self.block();
+        self.loop_depth -= 1;
+
+        body
+    }
+
+    fn loop_control_statement(&mut self) -> Result<Stmt, WmdError> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            let msg = format!("Can't use '{}' outside of a loop.", keyword.lexeme);
+            return Err(self.error(&keyword, &msg));
+        }
+
+        self.consume(
+            TokenType::SemiColon,
+            &format!("Expect ';' after '{}'.", keyword.lexeme),
+        )?;
+
+        if keyword.typ == TokenType::Break {
+            Ok(Stmt::Break(keyword.line))
+        } else {
+            Ok(Stmt::Continue(keyword.line))
+        }
     }
 
+    fn return_statement(&mut self) -> Result<Stmt, WmdError> {
+        let keyword = self.previous().clone();
+        if self.fn_depth == 0 {
+            return Err(self.error(&keyword, "Can't return from top-level code."));
+        }
+
+        let value = if self.check(TokenType::SemiColon) {
+            Expr::Literal(Literal::Nil)
+        } else {
+            self.expression()?
+        };
+
+        self.consume(TokenType::SemiColon, "Expect ';' after return value.")?;
+        Ok(Stmt::Return(keyword.line, value))
+    }
+
+    /// Block expressions may omit the trailing ';' as their closing '}'
+    /// already marks the end of the statement. Any expression may omit it
+    /// when it is the final expression of an enclosing block.
     fn expression_statement(&mut self) -> Result<Stmt, WmdError> {
         let expr = self.expression()?;
-        self.consume(TokenType::SemiColon, "Expect ';' after expression.")?;
+
+        if expr.is_block_like() || self.check(TokenType::RBrace) {
+            match_tok!(self, TokenType::SemiColon);
+        } else {
+            self.consume(TokenType::SemiColon, "Expect ';' after expression.")?;
+        }
+
         Ok(Stmt::Expr(expr))
     }
 
     fn expression(&mut self) -> Result<Expr, WmdError> {
         if match_tok!(self, TokenType::LBrace) {
             self.block()
+        } else if match_tok!(self, TokenType::If) {
+            self.if_expression()
+        } else if match_tok!(
+            self,
+            TokenType::Superset,
+            TokenType::Circuit,
+            TokenType::Emom,
+            TokenType::Amrap,
+            TokenType::Tabata
+        ) {
+            self.structure()
+        } else {
+            self.assignment()
+        }
+    }
+
+    /// Assumes the structure keyword has already been consumed. Circuits
+    /// take a number of rounds, `circuit 3 rounds {}`, while emoms and
+    /// amraps take their duration, `emom 10m {}`.
+    fn structure(&mut self) -> Result<Expr, WmdError> {
+        let keyword = self.previous();
+        let line = keyword.line;
+        let typ = match keyword.typ {
+            TokenType::Superset => StructureType::Superset,
+            TokenType::Circuit => StructureType::Circuit,
+            TokenType::Emom => StructureType::Emom,
+            TokenType::Amrap => StructureType::Amrap,
+            _ => StructureType::Tabata,
+        };
+
+        let arg = match typ {
+            StructureType::Circuit => {
+                let rounds = self.term()?;
+                self.consume_word("rounds", "Expect 'rounds' after circuit rounds.")?;
+                Some(Box::new(rounds))
+            }
+            StructureType::Emom | StructureType::Amrap => Some(Box::new(self.term()?)),
+            _ => None,
+        };
+
+        self.consume(TokenType::LBrace, "Expect '{' before structure body.")?;
+        let body = self.block()?;
+
+        Ok(Expr::Structure(typ, line, arg, Box::new(body)))
+    }
+
+    /// Assumes that the 'if' has already been consumed.
+    /// Both branches must be blocks, though 'else if' chains are permitted
+    fn if_expression(&mut self) -> Result<Expr, WmdError> {
+        let condition = self.or()?;
+
+        self.consume(TokenType::LBrace, "Expect '{' after if condition.")?;
+        let then_branch = self.block()?;
+
+        let else_branch = if match_tok!(self, TokenType::Else) {
+            if match_tok!(self, TokenType::If) {
+                Some(Box::new(self.if_expression()?))
+            } else {
+                self.consume(TokenType::LBrace, "Expect '{' after else.")?;
+                Some(Box::new(self.block()?))
+            }
+        } else {
+            None
+        };
+
+        Ok(Expr::If(
+            Box::new(condition),
+            Box::new(then_branch),
+            else_branch,
+        ))
+    }
+
+    /// Compound assignments are desugared, `a += b` becomes `a = a + b`
+    fn assignment(&mut self) -> Result<Expr, WmdError> {
+        let expr = self.or()?;
+
+        if match_tok!(
+            self,
+            TokenType::Equal,
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual
+        ) {
+            let equals = self.previous().clone();
+            let value = self.expression()?;
+
+            let ident = match expr {
+                Expr::Var(ident) => ident,
+                _ => return Err(self.error(&equals, "Invalid assignment target.")),
+            };
+
+            let op = match equals.typ {
+                TokenType::PlusEqual => Some(BinaryOp::Plus),
+                TokenType::MinusEqual => Some(BinaryOp::Minus),
+                TokenType::StarEqual => Some(BinaryOp::Star),
+                TokenType::SlashEqual => Some(BinaryOp::Slash),
+                _ => None,
+            };
+
+            let value = match op {
+                Some(typ) => Expr::Binary(
+                    Box::new(Expr::Var(ident.clone())),
+                    OpToken {
+                        typ,
+                        line: equals.line,
+                    },
+                    Box::new(value),
+                ),
+                None => value,
+            };
+
+            Ok(Expr::Assign(ident, Box::new(value)))
         } else {
-            self.or()
+            Ok(expr)
         }
     }
 
@@ -101,15 +424,19 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
         let mut stmts = Vec::new();
 
         while !self.check(TokenType::RBrace) && !self.is_at_end() {
-            match self.declaration() {
-                Ok(s) => stmts.push(s),
-                Err(e) => todo!(),
-            }
+            stmts.push(self.declaration()?);
+        }
+
+        // The final expression is the block's value only if it wasn't
+        // terminated by a semicolon
+        let has_tail = matches!(stmts.last(), Some(Stmt::Expr(_)))
+            && self.previous().typ != TokenType::SemiColon;
+        if !has_tail {
+            stmts.push(Stmt::Expr(Expr::Literal(Literal::Nil)));
         }
 
         self.consume(TokenType::RBrace, "Expect '}' after block.")?;
-        todo!()
-        // Ok(Expr::Block(stmts))
+        Ok(Expr::Block(stmts))
     }
 
     fn or(&mut self) -> Result<Expr, WmdError> {
@@ -152,7 +479,7 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
     }
 
     fn comparison(&mut self) -> Result<Expr, WmdError> {
-        let mut expr = self.term()?;
+        let mut expr = self.range()?;
 
         while match_tok!(
             self,
@@ -161,6 +488,35 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
             TokenType::Less,
             TokenType::LessEqual
         ) {
+            let operator = self.previous().try_into()?;
+            let right = self.range()?;
+
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(right))
+        }
+
+        Ok(expr)
+    }
+
+    /// Ranges don't chain, `a..b..c` is an error
+    fn range(&mut self) -> Result<Expr, WmdError> {
+        let expr = self.at()?;
+
+        if match_tok!(self, TokenType::DotDot, TokenType::DotDotEqual) {
+            let operator = self.previous().try_into()?;
+            let right = self.at()?;
+
+            Ok(Expr::Binary(Box::new(expr), operator, Box::new(right)))
+        } else {
+            Ok(expr)
+        }
+    }
+
+    /// Binds looser than arithmetic so a load can be computed,
+    /// `5x5 @ base + 5kg`
+    fn at(&mut self) -> Result<Expr, WmdError> {
+        let mut expr = self.term()?;
+
+        while match_tok!(self, TokenType::At) {
             let operator = self.previous().try_into()?;
             let right = self.term()?;
 
@@ -197,16 +553,51 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
     }
 
     fn unary(&mut self) -> Result<Expr, WmdError> {
-        if match_tok!(self, TokenType::Bang, TokenType::Minus) {
+        if match_tok!(self, TokenType::Bang, TokenType::Minus, TokenType::At) {
             let operator = self.previous().try_into()?;
             let right = self.unary()?;
 
             Ok(Expr::Unary(operator, Box::new(right)))
         } else {
-            self.primary()
+            self.call()
         }
     }
 
+    fn call(&mut self) -> Result<Expr, WmdError> {
+        let mut expr = self.primary()?;
+
+        while match_tok!(self, TokenType::LParen) {
+            let mut args = Vec::new();
+            let mut named = Vec::new();
+            if !self.check(TokenType::RParen) {
+                loop {
+                    if self.check(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+                        let name = self.advance().try_into()?;
+                        // The ':'
+                        self.advance();
+                        named.push((name, self.expression()?));
+                    } else if named.is_empty() {
+                        args.push(self.expression()?);
+                    } else {
+                        return Err(self.error(
+                            self.peek(),
+                            "Expect named arguments after positional arguments.",
+                        ));
+                    }
+
+                    if !match_tok!(self, TokenType::Comma) {
+                        break;
+                    }
+                }
+            }
+
+            let paren = self.consume(TokenType::RParen, "Expect ')' after arguments.")?;
+            expr = Expr::Call(Box::new(expr), paren.line, args, named);
+        }
+
+        Ok(expr)
+    }
+
     fn primary(&mut self) -> Result<Expr, WmdError> {
         if match_tok!(self, TokenType::False) {
             Ok(Expr::Literal(Literal::Bool(false)))
@@ -218,6 +609,8 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
             self,
             TokenType::Number,
             TokenType::Quantity,
+            TokenType::Prescription,
+            TokenType::Tempo,
             TokenType::String
         ) {
             let literal = self.previous_mut().literal.take().unwrap();
@@ -241,6 +634,8 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
 
             self.consume(TokenType::RBracket, "Expect ']' after list.")?;
             Ok(Expr::List(exprs))
+        } else if match_tok!(self, TokenType::Fn) {
+            Ok(Expr::Lambda(Rc::new(self.function(None)?)))
         } else if match_tok!(self, TokenType::LParen) {
             let expr = self.expression()?;
 
@@ -284,7 +679,13 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
         }
     }
 
-    fn consume(&mut self, typ: TokenType, msg: &str) -> Result<&Token, WmdError> {
+    fn check_next(&self, typ: TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|t| t.typ == typ)
+    }
+
+    fn consume(&mut self, typ: TokenType, msg: &str) -> Result<&Token<'source>, WmdError> {
         if self.check(typ) {
             Ok(self.advance())
         } else {
@@ -292,6 +693,22 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
         }
     }
 
+    /// Consumes an identifier that acts as a keyword in context
+    fn consume_word(&mut self, word: &str, msg: &str) -> Result<&Token<'source>, WmdError> {
+        if self.check(TokenType::Identifier) && self.peek().lexeme == word {
+            Ok(self.advance())
+        } else {
+            Err(self.error(self.peek(), msg))
+        }
+    }
+
+    fn string(&mut self, msg: &str) -> Result<String, WmdError> {
+        match &self.consume(TokenType::String, msg)?.literal {
+            Some(TokenLiteral::String(s)) => Ok(s.clone()),
+            _ => Err(self.error(self.previous(), msg)),
+        }
+    }
+
     fn error(&self, token: &Token, msg: &str) -> WmdError {
         self.reporter.error_token(token, msg);
         WmdError::ParseError
@@ -307,7 +724,12 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
             }
 
             match self.peek().typ {
-                TokenType::Fn | TokenType::For | TokenType::If | TokenType::While => return,
+                TokenType::Fn
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Exercise
+                | TokenType::Program => return,
                 _ => {}
             }
 
@@ -318,11 +740,210 @@ impl<'source, R: ErrorReporter> Parser<'source, R> {
 
 #[cfg(test)]
 mod tests {
+    use crate::{lexer::Lexer, reporting::StdoutReporter};
+
     use super::*;
 
+    fn parse(src: &str) -> Vec<Stmt> {
+        let reporter = StdoutReporter;
+        let tokens = Lexer::new(src, &reporter).scan_tokens();
+        Parser::new(tokens, &reporter).parse().unwrap()
+    }
+
     #[test]
     fn parser() {
-        let content = "true";
-        panic!()
+        let stmts = parse("true;");
+        assert_eq!(stmts, vec![Stmt::Expr(Expr::Literal(Literal::Bool(true)))]);
+    }
+
+    #[test]
+    fn assignment() {
+        let ident = IdentToken {
+            ident: "a".into(),
+            line: 1,
+        };
+        let stmts = parse("a += 1; 1 = 2; a = 3;");
+        assert_eq!(
+            stmts,
+            vec![
+                Stmt::Expr(Expr::Assign(
+                    ident.clone(),
+                    Box::new(Expr::Binary(
+                        Box::new(Expr::Var(ident.clone())),
+                        OpToken {
+                            typ: BinaryOp::Plus,
+                            line: 1
+                        },
+                        Box::new(Expr::Literal(Literal::Number(1.0)))
+                    ))
+                )),
+                Stmt::None,
+                Stmt::Expr(Expr::Assign(
+                    ident,
+                    Box::new(Expr::Literal(Literal::Number(3.0)))
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn if_else_chain() {
+        let stmts = parse("if a { 1 } else if b { 2 } else { 3 } 4;");
+        assert_eq!(stmts.len(), 2);
+
+        let (cond, else_branch) = match &stmts[0] {
+            Stmt::Expr(Expr::If(cond, _, Some(else_branch))) => (cond, else_branch),
+            s => panic!("expected if, found {s:?}"),
+        };
+        assert!(matches!(**cond, Expr::Var(_)));
+        assert!(matches!(**else_branch, Expr::If(_, _, Some(_))));
+    }
+
+    #[test]
+    fn loop_control_outside_loop() {
+        assert_eq!(parse("break; continue;"), vec![Stmt::None, Stmt::None]);
+
+        let stmts = parse("while true { if a { break; } continue; }");
+        assert!(matches!(stmts[..], [Stmt::While(..)]));
+    }
+
+    #[test]
+    fn functions() {
+        let stmts =
+            parse("fn add(a, b) { a + b } let f = fn() { return 1; }; f()(2, 3); return 1;");
+        assert_eq!(stmts.len(), 4);
+
+        match &stmts[0] {
+            Stmt::Fn(decl) => {
+                assert_eq!(decl.name.as_ref().unwrap().ident, "add");
+                assert_eq!(decl.params.len(), 2);
+            }
+            s => panic!("expected fn, found {s:?}"),
+        }
+        assert!(matches!(&stmts[1], Stmt::Let(_, Expr::Lambda(_))));
+        assert!(
+            matches!(&stmts[2], Stmt::Expr(Expr::Call(callee, _, args, _))
+            if args.len() == 2 && matches!(**callee, Expr::Call(..)))
+        );
+        assert_eq!(stmts[3], Stmt::None);
+
+        // Positional arguments can't follow named ones
+        let stmts = parse("plates(100kg, bar: 15kg); plates(bar: 15kg, 100kg);");
+        assert_eq!(stmts[1], Stmt::None);
+        match &stmts[0] {
+            Stmt::Expr(Expr::Call(_, _, args, named)) => {
+                assert_eq!(args.len(), 1);
+                assert_eq!(named[0].0.ident, "bar");
+            }
+            s => panic!("expected call, found {s:?}"),
+        }
+
+        // A parameter can only be named once
+        let stmts = parse("fn f(a, a) { a } fn g(a, b) { a }");
+        assert_eq!(stmts[0], Stmt::None);
+        assert!(matches!(stmts.last(), Some(Stmt::Fn(_))));
+    }
+
+    #[test]
+    fn structures() {
+        let stmts =
+            parse("circuit 3 rounds { a(5x) } emom 10m { b(1x) } 1; tabata {} circuit 3 {}");
+        assert_eq!(stmts.len(), 5);
+
+        match &stmts[0] {
+            Stmt::Expr(Expr::Structure(StructureType::Circuit, 1, Some(rounds), body)) => {
+                assert_eq!(**rounds, Expr::Literal(Literal::Number(3.0)));
+                assert!(matches!(**body, Expr::Block(_)));
+            }
+            s => panic!("expected circuit, found {s:?}"),
+        }
+        assert!(matches!(
+            &stmts[1],
+            Stmt::Expr(Expr::Structure(StructureType::Emom, _, Some(_), _))
+        ));
+        assert!(matches!(
+            &stmts[3],
+            Stmt::Expr(Expr::Structure(StructureType::Tabata, _, None, _))
+        ));
+        assert_eq!(stmts[4], Stmt::None);
+    }
+
+    #[test]
+    fn programs() {
+        let stmts = parse(
+            r#"let week = 1;
+            program "Block" {
+                week 1..=3 { day "A" { squat(5x5); } day "B" {} }
+                week 4 {}
+            }
+            program "Bad" { day "A" {} }"#,
+        );
+        assert_eq!(stmts.len(), 3);
+
+        assert!(matches!(&stmts[0], Stmt::Let(..)));
+        match &stmts[1] {
+            Stmt::Program(decl) => {
+                assert_eq!(decl.name, "Block");
+                assert_eq!(decl.weeks.len(), 2);
+                assert_eq!(decl.weeks[0].line, 3);
+                assert!(matches!(decl.weeks[0].weeks, Expr::Binary(..)));
+                let days: Vec<_> = decl.weeks[0].days.iter().map(|d| d.name.as_str()).collect();
+                assert_eq!(days, vec!["A", "B"]);
+                assert!(decl.weeks[1].days.is_empty());
+            }
+            s => panic!("expected program, found {s:?}"),
+        }
+        assert_eq!(stmts[2], Stmt::None);
+    }
+
+    #[test]
+    fn exercises() {
+        let stmts = parse(
+            r#"exercise squat { name: "Back Squat", aliases: ["bs"], }
+            exercise plank {}
+            exercise bad { name "x" }"#,
+        );
+        assert_eq!(stmts.len(), 3);
+
+        match &stmts[0] {
+            Stmt::Exercise(decl) => {
+                assert_eq!(decl.ident.ident, "squat");
+                let names: Vec<_> = decl.fields.iter().map(|(f, _)| f.ident.as_str()).collect();
+                assert_eq!(names, vec!["name", "aliases"]);
+            }
+            s => panic!("expected exercise, found {s:?}"),
+        }
+        assert!(matches!(&stmts[1], Stmt::Exercise(decl) if decl.fields.is_empty()));
+        assert_eq!(stmts[2], Stmt::None);
+    }
+
+    #[test]
+    fn ranges() {
+        let stmts = parse("1 + 1..=n * 2; 1..2..3;");
+        match &stmts[0] {
+            Stmt::Expr(Expr::Binary(start, op, end)) => {
+                assert_eq!(op.typ, BinaryOp::DotDotEqual);
+                assert!(matches!(**start, Expr::Binary(..)));
+                assert!(matches!(**end, Expr::Binary(..)));
+            }
+            s => panic!("expected range, found {s:?}"),
+        }
+        assert_eq!(stmts[1], Stmt::None);
+    }
+
+    #[test]
+    fn block_tail() {
+        let nil = Stmt::Expr(Expr::Literal(Literal::Nil));
+        let one = Stmt::Expr(Expr::Literal(Literal::Number(1.0)));
+
+        let stmts = parse("{ 1 } { 1; } {}");
+        assert_eq!(
+            stmts,
+            vec![
+                Stmt::Expr(Expr::Block(vec![one.clone()])),
+                Stmt::Expr(Expr::Block(vec![one, nil.clone()])),
+                Stmt::Expr(Expr::Block(vec![nil])),
+            ]
+        );
     }
 }
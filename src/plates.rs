@@ -0,0 +1,215 @@
+use crate::{
+    ast::{Quantity, Unit, WeightUnit},
+    WmdError,
+};
+
+/// Tolerance when matching plates to a load, in kilograms
+const EPSILON: f64 = 1e-6;
+
+/// Bar and the plates available to load it, each counted in pairs as
+/// plates go on both sides
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlateInventory {
+    bar: Quantity,
+    /// Heaviest first
+    plates: Vec<(Quantity, u32)>,
+}
+
+impl PlateInventory {
+    pub fn new(bar: Quantity) -> Self {
+        Self {
+            bar,
+            plates: Vec::new(),
+        }
+    }
+
+    /// Non weight plates are ignored
+    pub fn with_plates(mut self, plate: Quantity, pairs: u32) -> Self {
+        if kilograms(plate).is_some() {
+            self.plates.push((plate, pairs));
+            self.plates
+                .sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        }
+        self
+    }
+
+    /// 20kg bar with plenty of the usual plates from 25kg down to 1.25kg
+    pub fn kilograms() -> Self {
+        Self::standard(
+            WeightUnit::Kilogram,
+            20.0,
+            &[25.0, 20.0, 15.0, 10.0, 5.0, 2.5, 1.25],
+        )
+    }
+
+    /// 45lb bar with plenty of the usual plates from 45lb down to 2.5lb
+    pub fn pounds() -> Self {
+        Self::standard(WeightUnit::Pound, 45.0, &[45.0, 35.0, 25.0, 10.0, 5.0, 2.5])
+    }
+
+    fn standard(unit: WeightUnit, bar: f64, plates: &[f64]) -> Self {
+        let weight = |v| Quantity::new(v, Unit::Weight(unit));
+        plates
+            .iter()
+            .fold(Self::new(weight(bar)), |inventory, plate| {
+                inventory.with_plates(weight(*plate), 10)
+            })
+    }
+
+    pub fn bar(&self) -> Quantity {
+        self.bar
+    }
+
+    /// Plates to put on each side of the bar, heaviest first. bar overrides
+    /// the bar of the inventory.
+    pub fn per_side(
+        &self,
+        load: Quantity,
+        bar: Option<Quantity>,
+    ) -> Result<Vec<Quantity>, WmdError> {
+        let unachievable = || WmdError::UnachievableLoad(load);
+        let bar = bar.unwrap_or(self.bar);
+        let side = (kilograms(load).ok_or_else(unachievable)?
+            - kilograms(bar).ok_or_else(unachievable)?)
+            / 2.0;
+        if side < -EPSILON || !is_multiple(side, increment(&self.plates)) {
+            return Err(unachievable());
+        }
+
+        let mut plates = Vec::new();
+        if fill(&self.plates, side, &mut plates) {
+            Ok(plates)
+        } else {
+            Err(unachievable())
+        }
+    }
+}
+
+impl Default for PlateInventory {
+    fn default() -> Self {
+        Self::kilograms()
+    }
+}
+
+fn kilograms(weight: Quantity) -> Option<f64> {
+    weight
+        .convert_to(Unit::Weight(WeightUnit::Kilogram))
+        .map(|w| w.value())
+}
+
+/// Largest weight every plate is a multiple of, in kilograms, so any
+/// achievable side is a multiple of it too
+fn increment(plates: &[(Quantity, u32)]) -> f64 {
+    plates
+        .iter()
+        .filter_map(|(plate, _)| kilograms(*plate))
+        .fold(0.0, |mut a, mut b| {
+            while b > EPSILON {
+                (a, b) = (b, a % b);
+                if b > a - EPSILON {
+                    b = 0.0;
+                }
+            }
+            a
+        })
+}
+
+fn is_multiple(weight: f64, increment: f64) -> bool {
+    if increment < EPSILON {
+        return weight.abs() < EPSILON;
+    }
+    let remainder = weight % increment;
+    remainder < EPSILON || increment - remainder < EPSILON
+}
+
+/// Depth first search for plates summing to side, trying as many of the
+/// heaviest plates as possible first so the fewest plates are used.
+/// Branches are cut once the remaining plates can't reach side.
+fn fill(plates: &[(Quantity, u32)], side: f64, chosen: &mut Vec<Quantity>) -> bool {
+    if side.abs() < EPSILON {
+        return true;
+    }
+
+    let remaining: f64 = plates
+        .iter()
+        .filter_map(|(plate, pairs)| Some(kilograms(*plate)? * *pairs as f64))
+        .sum();
+    if remaining < side - EPSILON {
+        return false;
+    }
+
+    let Some(((plate, pairs), rest)) = plates.split_first() else {
+        return false;
+    };
+    let weight = kilograms(*plate).unwrap_or(f64::INFINITY);
+    let most = ((side + EPSILON) / weight).floor().min(*pairs as f64) as usize;
+
+    let len = chosen.len();
+    for count in (0..=most).rev() {
+        chosen.extend(std::iter::repeat_n(*plate, count));
+        if fill(rest, side - count as f64 * weight, chosen) {
+            return true;
+        }
+        chosen.truncate(len);
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plates_per_side() {
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+        let lb = |v| Quantity::new(v, Unit::Weight(WeightUnit::Pound));
+
+        let inventory = PlateInventory::kilograms();
+        assert_eq!(
+            inventory.per_side(kg(142.5), None).unwrap(),
+            vec![kg(25.0), kg(25.0), kg(10.0), kg(1.25)]
+        );
+        assert_eq!(inventory.per_side(kg(20.0), None).unwrap(), vec![]);
+        assert_eq!(
+            inventory.per_side(kg(60.0), Some(kg(15.0))).unwrap(),
+            vec![kg(20.0), kg(2.5)]
+        );
+        assert!(matches!(
+            inventory.per_side(kg(101.0), None),
+            Err(WmdError::UnachievableLoad(_))
+        ));
+        assert!(inventory.per_side(kg(10.0), None).is_err());
+
+        assert_eq!(
+            PlateInventory::pounds().per_side(lb(315.0), None).unwrap(),
+            vec![lb(45.0); 3]
+        );
+
+        // Limited plates are searched rather than picked greedily
+        let inventory = PlateInventory::new(kg(20.0))
+            .with_plates(kg(15.0), 1)
+            .with_plates(kg(10.0), 2);
+        assert_eq!(
+            inventory.per_side(kg(60.0), None).unwrap(),
+            vec![kg(10.0), kg(10.0)]
+        );
+        // 25kg is made of 15kg and 10kg though it isn't a multiple of either
+        assert_eq!(
+            inventory.per_side(kg(70.0), None).unwrap(),
+            vec![kg(15.0), kg(10.0)]
+        );
+
+        // Heavy loads that can't be made fail without searching every
+        // combination of plates
+        let inventory = PlateInventory::kilograms();
+        for load in [1000.5, 1597.5, 2000.0] {
+            assert!(matches!(
+                inventory.per_side(kg(load), None),
+                Err(WmdError::UnachievableLoad(_))
+            ));
+        }
+        assert_eq!(inventory.per_side(kg(1595.0), None).unwrap().len(), 70);
+        assert!(PlateInventory::pounds().per_side(lb(1001.0), None).is_err());
+    }
+}
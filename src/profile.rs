@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{Quantity, Unit},
+    exercise::Exercise,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Nearest,
+    Down,
+    Up,
+}
+
+/// Maxes of a lifter per exercise, used to turn percentage loads such as
+/// `squat(5x5 @ 80%)` into absolute loads. Whether a max is a true one rep
+/// max or a training max is up to the host.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LifterProfile {
+    /// Keyed by lowercase exercise id, name or alias
+    maxes: HashMap<String, Quantity>,
+    rounding: Option<(Quantity, Rounding)>,
+}
+
+impl LifterProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// exercise is matched case insensitively against the id, name and
+    /// aliases of declared exercises
+    pub fn with_max(mut self, exercise: &str, max: Quantity) -> Self {
+        self.maxes.insert(exercise.to_lowercase(), max);
+        self
+    }
+
+    /// Rounds resolved loads to a multiple of increment, e.g. `5lb` when
+    /// the smallest plates are 2.5lb
+    pub fn with_rounding(mut self, increment: Quantity, rounding: Rounding) -> Self {
+        self.rounding = Some((increment, rounding));
+        self
+    }
+
+    pub fn max(&self, exercise: &Exercise) -> Option<Quantity> {
+        std::iter::once(&exercise.id)
+            .chain(std::iter::once(&exercise.name))
+            .chain(&exercise.aliases)
+            .find_map(|name| self.maxes.get(&name.to_lowercase()))
+            .copied()
+    }
+
+    /// Absolute load for a percentage of the exercise's max in the unit of
+    /// the max. `None` if load isn't a percentage or there is no max.
+    pub fn resolve(&self, exercise: &Exercise, load: Quantity) -> Option<Quantity> {
+        if load.unit() != Unit::Percent {
+            return None;
+        }
+
+        let max = self.max(exercise)?;
+        Some(self.round(max.scale(load.value() / 100.0)))
+    }
+
+    /// Loads in a unit incompatible with the increment are left unrounded
+    pub fn round(&self, load: Quantity) -> Quantity {
+        let Some((increment, rounding)) = self.rounding else {
+            return load;
+        };
+        let Some(increment) = increment.convert_to(load.unit()) else {
+            return load;
+        };
+
+        let steps = load.value() / increment.value();
+        let steps = match rounding {
+            Rounding::Nearest => steps.round(),
+            Rounding::Down => steps.floor(),
+            Rounding::Up => steps.ceil(),
+        };
+        Quantity::new(steps * increment.value(), load.unit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::WeightUnit;
+
+    use super::*;
+
+    #[test]
+    fn resolve_percentages() {
+        let kg = |v| Quantity::new(v, Unit::Weight(WeightUnit::Kilogram));
+        let pct = |v| Quantity::new(v, Unit::Percent);
+
+        let mut squat = Exercise::new("squat");
+        squat.aliases = vec!["Back Squat".to_owned()];
+
+        let profile = LifterProfile::new().with_max("back squat", kg(145.0));
+        assert_eq!(profile.resolve(&squat, pct(80.0)), Some(kg(116.0)));
+        assert_eq!(profile.resolve(&squat, kg(100.0)), None);
+        assert_eq!(profile.resolve(&Exercise::new("bench"), pct(80.0)), None);
+
+        let profile = profile.with_rounding(kg(2.5), Rounding::Nearest);
+        assert_eq!(profile.resolve(&squat, pct(80.0)), Some(kg(115.0)));
+
+        let profile = profile.with_rounding(kg(2.5), Rounding::Up);
+        assert_eq!(profile.resolve(&squat, pct(80.0)), Some(kg(117.5)));
+
+        // Increments convert to the unit of the max
+        let lb = |v| Quantity::new(v, Unit::Weight(WeightUnit::Pound));
+        let profile = LifterProfile::new()
+            .with_max("squat", lb(315.0))
+            .with_rounding(lb(5.0), Rounding::Down);
+        assert_eq!(profile.resolve(&squat, pct(85.0)).unwrap().value(), 265.0);
+    }
+}
@@ -1,4 +1,9 @@
-use crate::lexer::{Token, TokenType};
+use std::{cell::RefCell, fmt::Display};
+
+use crate::{
+    lexer::{Token, TokenType},
+    WmdError,
+};
 
 pub trait ErrorReporter {
     fn error(&self, line: usize, msg: &str) {
@@ -18,8 +23,84 @@ pub trait ErrorReporter {
 
 pub struct StdoutReporter;
 
-impl<'a> ErrorReporter for &'a StdoutReporter {
+impl ErrorReporter for &StdoutReporter {
     fn report(&self, line: usize, whre: String, msg: &str) {
         eprintln!("[line {line}] Error{whre}: {msg}")
     }
 }
+
+/// A single error found while running a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// `None` for errors not tied to a line of the source
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "[line {line}] {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Collects errors rather than printing them so hosts can present them
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: RefCell<Vec<Diagnostic>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The line prefix of a runtime error's message moves to the line of
+    /// the diagnostic
+    pub fn push(&self, error: &WmdError) {
+        let line = error.line();
+        let message = error.to_string();
+        let message = match line {
+            Some(line) => message
+                .strip_prefix(&format!("[line {line}] "))
+                .map(str::to_owned)
+                .unwrap_or(message),
+            None => message,
+        };
+
+        self.diagnostics
+            .borrow_mut()
+            .push(Diagnostic { line, message })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.borrow().is_empty()
+    }
+
+    pub fn into_inner(self) -> Vec<Diagnostic> {
+        self.diagnostics.into_inner()
+    }
+}
+
+impl ErrorReporter for &Diagnostics {
+    fn report(&self, line: usize, whre: String, msg: &str) {
+        self.diagnostics.borrow_mut().push(Diagnostic {
+            line: Some(line),
+            message: format!("Error{whre}: {msg}"),
+        })
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for diagnostic in self.diagnostics.borrow().iter() {
+            writeln!(f, "{diagnostic}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
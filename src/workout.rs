@@ -0,0 +1,500 @@
+use std::{fmt::Display, rc::Rc, time::Duration};
+
+use crate::{
+    ast::{Prescription, Quantity, Reps, Tempo, TimeUnit, Unit},
+    exercise::Exercise,
+};
+
+/// Exercise performed for a time or distance rather than for reps,
+/// `plank(60s)` or `row(500mtr)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Work {
+    pub exercise: Rc<Exercise>,
+    pub amount: Quantity,
+}
+
+impl Work {
+    /// `None` if the work is a distance
+    pub fn duration(&self) -> Option<Duration> {
+        self.amount.to_duration()
+    }
+}
+
+impl Display for Work {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.exercise, self.amount)
+    }
+}
+
+/// Anything collected from the body of a workout structure
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Sets(Prescription),
+    Work(Work),
+    Rest(Quantity),
+    Structure(Rc<Structure>),
+}
+
+impl Item {
+    /// Single sets, work and rest in the order they are performed, with
+    /// structures laid out round by round. The sets, rests and timeline
+    /// of a workout are all derived from these steps.
+    pub fn steps(&self) -> Vec<Step> {
+        match self {
+            Item::Sets(p) => p
+                .expand()
+                .into_iter()
+                .map(Item::Sets)
+                .map(Step::new)
+                .collect(),
+            Item::Structure(s) => s.steps(),
+            item => vec![Step::new(item.clone())],
+        }
+    }
+
+    /// The items of [`Item::steps`]
+    pub fn flatten(&self) -> Vec<Item> {
+        self.steps().into_iter().map(|step| step.item).collect()
+    }
+
+    /// Segments for performing the item once, each set of a
+    /// prescription is its own segment
+    pub fn segments(&self) -> Vec<Segment> {
+        self.steps().iter().map(Step::segment).collect()
+    }
+
+    /// Steps for a single round of a structure, only the first set of a
+    /// prescription
+    fn once(&self) -> Vec<Step> {
+        match self {
+            Item::Sets(p) => p
+                .expand()
+                .into_iter()
+                .take(1)
+                .map(Item::Sets)
+                .map(Step::new)
+                .collect(),
+            item => item.steps(),
+        }
+    }
+
+    /// Steps for a round given a fixed interval, untimed work such as a
+    /// set of reps takes the whole interval
+    fn in_interval(&self, interval: Duration) -> Vec<Step> {
+        if let Item::Rest(_) = self {
+            return vec![Step::rest(interval)];
+        }
+
+        let mut steps = self.once();
+        if let [step] = &mut steps[..] {
+            step.duration = Some(step.duration.map_or(interval, |d| d.min(interval)));
+        }
+        steps
+    }
+}
+
+/// One item of a workout as it is performed, see [`Item::steps`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// A single set, work, rest, or an amrap performed as a whole
+    pub item: Item,
+    /// Time the step takes, set by the structure for interval work
+    pub duration: Option<Duration>,
+}
+
+impl Step {
+    fn new(item: Item) -> Self {
+        let duration = match &item {
+            Item::Work(w) => w.duration(),
+            Item::Rest(r) => r.to_duration(),
+            _ => None,
+        };
+        Self { item, duration }
+    }
+
+    fn rest(duration: Duration) -> Self {
+        Self {
+            item: Item::Rest(seconds(duration)),
+            duration: Some(duration),
+        }
+    }
+
+    pub fn segment(&self) -> Segment {
+        match &self.item {
+            Item::Rest(_) => Segment::rest(self.duration),
+            item => Segment::work(item.to_string(), self.duration),
+        }
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Item::Sets(p) => write!(f, "{p}"),
+            Item::Work(w) => write!(f, "{w}"),
+            Item::Rest(r) => write!(f, "rest {r}"),
+            Item::Structure(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StructureKind {
+    /// Items alternate set by set
+    Superset,
+    /// Every item is performed once per round
+    Circuit(u32),
+    /// Items take turns starting on each minute
+    Emom(Quantity),
+    /// As many rounds of the items as possible in the time
+    Amrap(Quantity),
+    /// Eight rounds of 20s work and 10s rest
+    Tabata,
+}
+
+impl StructureKind {
+    /// Most rounds a circuit may ask for
+    pub const MAX_ROUNDS: u32 = 100;
+    /// Longest an emom or amrap may last, in minutes
+    pub const MAX_MINUTES: u32 = 120;
+}
+
+impl Display for StructureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StructureKind::Superset => write!(f, "superset"),
+            StructureKind::Circuit(rounds) => write!(f, "circuit {rounds} rounds"),
+            StructureKind::Emom(d) => write!(f, "emom {d}"),
+            StructureKind::Amrap(d) => write!(f, "amrap {d}"),
+            StructureKind::Tabata => write!(f, "tabata"),
+        }
+    }
+}
+
+/// Workout block such as `circuit 3 rounds { ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structure {
+    pub kind: StructureKind,
+    pub items: Vec<Item>,
+}
+
+impl Structure {
+    /// Every step performed in order, see [`Item::steps`]. Each minute of
+    /// an emom is work followed by rest for the remainder of the minute.
+    /// Amraps are a single step as the number of rounds isn't known.
+    pub fn steps(&self) -> Vec<Step> {
+        match self.kind {
+            StructureKind::Superset => {
+                let rounds = self
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        Item::Sets(p) => Some(p.sets),
+                        _ => None,
+                    })
+                    .max()
+                    .unwrap_or(1);
+
+                (0..rounds)
+                    .flat_map(|round| {
+                        self.items.iter().flat_map(move |item| match item {
+                            Item::Sets(p) if p.sets <= round => Vec::new(),
+                            item => item.once(),
+                        })
+                    })
+                    .collect()
+            }
+            StructureKind::Circuit(rounds) => (0..rounds)
+                .flat_map(|_| self.items.iter().flat_map(Item::steps))
+                .collect(),
+            StructureKind::Emom(duration) => {
+                let minute = Duration::from_secs(60);
+                self.minute_items(duration)
+                    .flat_map(|item| {
+                        let mut steps = item.in_interval(minute);
+                        let taken = steps.iter().map(|s| s.duration).sum::<Option<Duration>>();
+                        if let Some(rest) = taken.and_then(|t| minute.checked_sub(t)) {
+                            if !rest.is_zero() {
+                                steps.push(Step::rest(rest));
+                            }
+                        }
+                        steps
+                    })
+                    .collect()
+            }
+            StructureKind::Amrap(duration) => vec![Step {
+                item: Item::Structure(Rc::new(self.clone())),
+                duration: duration.to_duration(),
+            }],
+            StructureKind::Tabata => self
+                .items
+                .iter()
+                .cycle()
+                .take(if self.items.is_empty() { 0 } else { 8 })
+                .flat_map(|item| {
+                    let mut steps = item.in_interval(Duration::from_secs(20));
+                    steps.push(Step::rest(Duration::from_secs(10)));
+                    steps
+                })
+                .collect(),
+        }
+    }
+
+    /// Item started on each minute of an emom, a partial minute is
+    /// dropped
+    fn minute_items(&self, duration: Quantity) -> impl Iterator<Item = &Item> {
+        let minutes = duration.to_seconds().unwrap_or(0.0) / 60.0;
+        self.items.iter().cycle().take(minutes as usize)
+    }
+
+    /// Lays the structure out as the work and rest it is performed as
+    pub fn segments(&self) -> Vec<Segment> {
+        self.steps().iter().map(Step::segment).collect()
+    }
+}
+
+impl Display for Structure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let items = self
+            .items
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{} {{ {items} }}", self.kind)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    Work,
+    Rest,
+    /// Moving on to a new section of the workout
+    Transition,
+}
+
+impl Display for SegmentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SegmentKind::Work => write!(f, "work"),
+            SegmentKind::Rest => write!(f, "rest"),
+            SegmentKind::Transition => write!(f, "transition"),
+        }
+    }
+}
+
+/// One step of a workout as it is performed. Duration is `None` when the
+/// work takes as long as it takes, such as a set of reps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub label: String,
+    pub duration: Option<Duration>,
+    /// Announces the work coming up after a rest or transition
+    pub cue: Option<String>,
+    /// Offset from the start of the workout, only set by
+    /// [`Workout::timeline`]
+    pub start: Duration,
+}
+
+impl Segment {
+    pub fn work(label: String, duration: Option<Duration>) -> Self {
+        Self::new(SegmentKind::Work, label, duration)
+    }
+
+    pub fn rest(duration: Option<Duration>) -> Self {
+        Self::new(SegmentKind::Rest, "rest".to_owned(), duration)
+    }
+
+    pub fn transition(label: String) -> Self {
+        Self::new(SegmentKind::Transition, label, None)
+    }
+
+    fn new(kind: SegmentKind, label: String, duration: Option<Duration>) -> Self {
+        Self {
+            kind,
+            label,
+            duration,
+            cue: None,
+            start: Duration::ZERO,
+        }
+    }
+}
+
+/// `0:30 rest 0:10 (next: squat 1x5)`
+impl Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#} {}", seconds(self.start), self.kind)?;
+        if self.kind != SegmentKind::Rest {
+            write!(f, " {}", self.label)?;
+        }
+        if let Some(duration) = self.duration {
+            write!(f, " {:#}", seconds(duration))?;
+        }
+        if let Some(cue) = &self.cue {
+            write!(f, " ({cue})")?;
+        }
+        Ok(())
+    }
+}
+
+fn seconds(duration: Duration) -> Quantity {
+    Quantity::new(duration.as_secs_f64(), Unit::Time(TimeUnit::Second))
+}
+
+/// What a single set asks for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Reps(Reps),
+    /// Time or distance of timed work
+    Amount(Quantity),
+}
+
+/// One set of a workout in the order it is performed
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetPrescription {
+    /// Index into the sections of the workout
+    pub section: usize,
+    pub exercise: Option<Rc<Exercise>>,
+    pub target: Target,
+    pub load: Option<Quantity>,
+    pub intensity: Option<Quantity>,
+    pub tempo: Option<Tempo>,
+}
+
+impl SetPrescription {
+    /// `None` unless item is a single set or work
+    fn of(section: usize, item: Item) -> Option<Self> {
+        match item {
+            Item::Sets(p) => Some(SetPrescription {
+                section,
+                exercise: p.exercise,
+                target: Target::Reps(p.reps),
+                load: p.load,
+                intensity: p.intensity,
+                tempo: p.tempo,
+            }),
+            Item::Work(w) => Some(SetPrescription {
+                section,
+                exercise: Some(w.exercise),
+                target: Target::Amount(w.amount),
+                load: None,
+                intensity: None,
+                tempo: None,
+            }),
+            Item::Rest(_) | Item::Structure(_) => None,
+        }
+    }
+}
+
+/// Rest taken once the first `after` sets of the workout are done
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rest {
+    pub after: usize,
+    pub duration: Duration,
+}
+
+/// Items recorded after a `section("name")` call, unnamed if recorded
+/// before any section was started
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: Option<String>,
+    pub items: Vec<Item>,
+}
+
+/// Everything a script describes, accumulated as its statements run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Workout {
+    pub name: Option<String>,
+    pub sections: Vec<Section>,
+    pub sets: Vec<SetPrescription>,
+    pub rests: Vec<Rest>,
+    pub notes: Vec<String>,
+}
+
+impl Workout {
+    pub fn start_section(&mut self, name: &str) {
+        self.sections.push(Section {
+            name: Some(name.to_owned()),
+            items: Vec::new(),
+        });
+    }
+
+    /// Adds item to the current section and its sets and rests to the
+    /// flattened lists
+    pub fn record(&mut self, item: Item) {
+        if self.sections.is_empty() {
+            self.sections.push(Section {
+                name: None,
+                items: Vec::new(),
+            });
+        }
+        let section = self.sections.len() - 1;
+
+        for step in item.steps() {
+            match step.item {
+                Item::Rest(_) => {
+                    if let Some(duration) = step.duration {
+                        self.rests.push(Rest {
+                            after: self.sets.len(),
+                            duration,
+                        });
+                    }
+                }
+                // Amraps record the sets of a single round
+                Item::Structure(amrap) => {
+                    let round = amrap.items.iter().flat_map(Item::flatten);
+                    self.sets
+                        .extend(round.filter_map(|item| SetPrescription::of(section, item)));
+                }
+                item => self.sets.extend(SetPrescription::of(section, item)),
+            }
+        }
+
+        self.sections[section].items.push(item);
+    }
+
+    /// Every segment of the workout in order with its start offset. Named
+    /// sections open with a transition, and rests and transitions are
+    /// cued with the work that follows them. Untimed segments such as sets
+    /// of reps count as taking no time.
+    pub fn timeline(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        for section in &self.sections {
+            if let Some(name) = &section.name {
+                segments.push(Segment::transition(name.clone()));
+            }
+            segments.extend(section.items.iter().flat_map(Item::segments));
+        }
+
+        let mut start = Duration::ZERO;
+        for i in 0..segments.len() {
+            segments[i].start = start;
+            start += segments[i].duration.unwrap_or_default();
+
+            if segments[i].kind != SegmentKind::Work {
+                segments[i].cue = segments[i + 1..]
+                    .iter()
+                    .find(|s| s.kind == SegmentKind::Work)
+                    .map(|s| format!("next: {}", s.label));
+            }
+        }
+
+        segments
+    }
+}
+
+/// Training block expanded from a `program` declaration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    pub name: String,
+    /// Ordered by week number
+    pub weeks: Vec<Week>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Week {
+    pub number: u32,
+    /// Named after their day
+    pub days: Vec<Workout>,
+}